@@ -0,0 +1,137 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+use crate::srt::SubtitleEntry;
+
+pub fn parse_ass(path: &Path) -> Result<Vec<SubtitleEntry>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read subtitle file: {}", path.display()))?;
+
+    Ok(parse_ass_str(&content))
+}
+
+pub fn parse_ass_str(content: &str) -> Vec<SubtitleEntry> {
+    // Override blocks such as {\i1} or {\pos(10,20)}
+    let override_re = Regex::new(r"\{[^}]*\}").unwrap();
+
+    let mut entries = Vec::new();
+    let mut in_events = false;
+    // Default [Events] field order for ASS; a Format: line overrides it
+    let mut fields: Vec<String> = "Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text"
+        .split(", ")
+        .map(str::to_string)
+        .collect();
+
+    for line in content.lines() {
+        let line = line.trim();
+
+        if line.starts_with('[') {
+            in_events = line.eq_ignore_ascii_case("[events]");
+            continue;
+        }
+        if !in_events {
+            continue;
+        }
+
+        if let Some(format) = line.strip_prefix("Format:") {
+            fields = format.split(',').map(|f| f.trim().to_string()).collect();
+            continue;
+        }
+
+        let Some(dialogue) = line.strip_prefix("Dialogue:") else {
+            continue;
+        };
+
+        // Text is the last field and may itself contain commas
+        let values: Vec<&str> = dialogue.trim_start().splitn(fields.len(), ',').collect();
+        if values.len() != fields.len() {
+            continue;
+        }
+
+        let field = |name: &str| {
+            fields
+                .iter()
+                .position(|f| f.eq_ignore_ascii_case(name))
+                .map(|i| values[i])
+        };
+
+        let (Some(start), Some(end), Some(text)) = (
+            field("Start").and_then(parse_ass_time),
+            field("End").and_then(parse_ass_time),
+            field("Text"),
+        ) else {
+            continue;
+        };
+
+        let text = override_re
+            .replace_all(text, "")
+            .replace("\\N", " ")
+            .replace("\\n", " ")
+            .replace("\\h", " ")
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        if !text.is_empty() {
+            entries.push(SubtitleEntry { index: 0, start, end, text });
+        }
+    }
+
+    // Events can be listed in any order; number the cues in time order like the other formats
+    entries.sort_by(|a, b| a.start.total_cmp(&b.start));
+    for (i, entry) in entries.iter_mut().enumerate() {
+        entry.index = i + 1;
+    }
+    entries
+}
+
+/// ASS timestamps are `H:MM:SS.cs` (centiseconds)
+fn parse_ass_time(ts: &str) -> Option<f64> {
+    let mut parts = ts.trim().split(':');
+    let h: f64 = parts.next()?.parse().ok()?;
+    let m: f64 = parts.next()?.parse().ok()?;
+    let s: f64 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some(h * 3600.0 + m * 60.0 + s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<SubtitleEntry> {
+        parse_ass(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sample.ass")).unwrap()
+    }
+
+    #[test]
+    fn reads_dialogue_lines_in_time_order() {
+        let entries = sample();
+        let cues: Vec<(f64, f64)> = entries.iter().map(|e| (e.start, e.end)).collect();
+        assert_eq!(cues, [(1.0, 2.0), (5.5, 7.25)]);
+    }
+
+    #[test]
+    fn numbers_cues_in_time_order() {
+        let indices: Vec<usize> = sample().iter().map(|e| e.index).collect();
+        assert_eq!(indices, [1, 2]);
+    }
+
+    #[test]
+    fn strips_override_tags_and_line_breaks() {
+        let entries = sample();
+        assert_eq!(entries[0].text, "First, in time order");
+        assert_eq!(entries[1].text, "Well, hello there");
+    }
+
+    #[test]
+    fn follows_the_format_line() {
+        let entries = parse_ass_str("[Events]\nFormat: Start, End, Text\nDialogue: 0:00:03.00,0:00:04.00,Hi, you\n");
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].start, entries[0].text.as_str()), (3.0, "Hi, you"));
+    }
+}
@@ -0,0 +1,151 @@
+use anyhow::{bail, Context, Result};
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+struct Job {
+    index: usize,
+    args: Vec<String>,
+}
+
+struct JobResult {
+    index: usize,
+    success: bool,
+    elapsed: Duration,
+}
+
+/// Read a batch file, or stdin for `-`: one clip per line, written as gifclip arguments
+/// (shell quoting applies, `#` starts a comment)
+fn load_jobs(path: &Path) -> Result<Vec<Job>> {
+    let from_stdin = path == Path::new("-");
+    let (content, source) = if from_stdin {
+        (io::read_to_string(io::stdin()).context("Failed to read batch from stdin")?, "stdin")
+    } else {
+        (
+            fs::read_to_string(path)
+                .with_context(|| format!("Failed to read batch file {}", path.display()))?,
+            "Batch file",
+        )
+    };
+
+    let mut jobs = Vec::new();
+    for (line_no, line) in content.lines().enumerate() {
+        let args = shell_words::split(line)
+            .with_context(|| format!("{} line {}: unbalanced quotes", source, line_no + 1))?;
+        // Blank and comment-only lines
+        if args.is_empty() {
+            continue;
+        }
+        jobs.push(Job {
+            index: jobs.len() + 1,
+            args,
+        });
+    }
+
+    if jobs.is_empty() {
+        if from_stdin {
+            bail!("No clips on stdin");
+        }
+        bail!("Batch file {} has no clips", path.display());
+    }
+    Ok(jobs)
+}
+
+/// Run every clip in a batch file, up to `jobs` encodes at a time.
+///
+/// Each clip runs as its own gifclip process (and so gets its own temp directory). Output
+/// is buffered per clip and printed in one block, prefixed with the clip number, when it
+/// finishes, so concurrent encodes don't interleave.
+pub fn run_batch(path: &Path, jobs: usize) -> Result<()> {
+    let queue = load_jobs(path)?;
+    let exe = env::current_exe().context("Failed to locate the gifclip executable")?;
+    let workers = jobs.clamp(1, queue.len());
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::new());
+    let output_lock = Mutex::new(());
+    let started = Instant::now();
+
+    println!("Running {} clips, {} at a time", queue.len(), workers);
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                while let Some(job) = queue.get(next.fetch_add(1, Ordering::SeqCst)) {
+                    let clip_start = Instant::now();
+                    let output = Command::new(&exe)
+                        .args(&job.args)
+                        .arg("--batch-index")
+                        .arg(job.index.to_string())
+                        .stdin(Stdio::null())
+                        .output();
+                    let elapsed = clip_start.elapsed();
+
+                    let success = {
+                        let _guard = output_lock.lock().unwrap();
+                        print_job_output(job, output.as_ref())
+                    };
+                    results.lock().unwrap().push(JobResult {
+                        index: job.index,
+                        success,
+                        elapsed,
+                    });
+                }
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|r| r.index);
+    let failed: Vec<usize> = results.iter().filter(|r| !r.success).map(|r| r.index).collect();
+    let busy: f64 = results.iter().map(|r| r.elapsed.as_secs_f64()).sum();
+
+    println!(
+        "\nBatch: {} of {} clips succeeded in {:.1}s ({:.1}s of encoding across {} jobs)",
+        results.len() - failed.len(),
+        results.len(),
+        started.elapsed().as_secs_f64(),
+        busy,
+        workers
+    );
+
+    if !failed.is_empty() {
+        let list: Vec<String> = failed.iter().map(|i| i.to_string()).collect();
+        bail!("Clips failed: {}", list.join(", "));
+    }
+    Ok(())
+}
+
+/// Print one finished clip's captured output, returning whether it succeeded
+fn print_job_output(job: &Job, output: Result<&std::process::Output, &io::Error>) -> bool {
+    let prefix = format!("[clip {}]", job.index);
+    let mut stdout = io::stdout().lock();
+
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => {
+            let _ = writeln!(stdout, "{} failed to start: {}", prefix, e);
+            return false;
+        }
+    };
+
+    for stream in [&output.stdout, &output.stderr] {
+        for line in String::from_utf8_lossy(stream).lines() {
+            // ffmpeg-style progress rewrites a line with \r; keep only the final state
+            let line = line.rsplit('\r').next().unwrap_or(line);
+            if !line.trim().is_empty() {
+                let _ = writeln!(stdout, "{} {}", prefix, line);
+            }
+        }
+    }
+
+    if !output.status.success() {
+        let _ = writeln!(stdout, "{} failed ({})", prefix, output.status);
+    }
+    output.status.success()
+}
@@ -0,0 +1,193 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Clone)]
+pub struct Chapter {
+    pub start: f64,
+    pub end: f64,
+    pub title: String,
+}
+
+#[derive(Deserialize)]
+struct YtDlpInfo {
+    #[serde(default)]
+    chapters: Option<Vec<YtDlpChapter>>,
+}
+
+#[derive(Deserialize)]
+struct YtDlpChapter {
+    start_time: f64,
+    end_time: f64,
+    #[serde(default)]
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct FfprobeChapters {
+    #[serde(default)]
+    chapters: Vec<FfprobeChapter>,
+}
+
+#[derive(Deserialize)]
+struct FfprobeChapter {
+    start_time: String,
+    end_time: String,
+    #[serde(default)]
+    tags: FfprobeTags,
+}
+
+#[derive(Deserialize, Default)]
+struct FfprobeTags {
+    title: Option<String>,
+}
+
+/// Read chapters from yt-dlp's JSON metadata (YouTube and other yt-dlp sites)
+pub fn from_yt_dlp(yt_dlp: &Path, url: &str) -> Result<Vec<Chapter>> {
+    let output = Command::new(yt_dlp)
+        .arg("-J")
+        .arg("--no-playlist")
+        .arg(url)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .context("Failed to run yt-dlp")?;
+
+    if !output.status.success() {
+        bail!("yt-dlp failed to fetch video metadata");
+    }
+
+    let info: YtDlpInfo =
+        serde_json::from_slice(&output.stdout).context("Failed to parse yt-dlp metadata")?;
+
+    Ok(info
+        .chapters
+        .unwrap_or_default()
+        .into_iter()
+        .map(|c| Chapter {
+            start: c.start_time,
+            end: c.end_time,
+            title: c.title,
+        })
+        .collect())
+}
+
+/// Read embedded chapters with ffprobe (local files and direct URLs)
+pub fn from_ffprobe(ffprobe: &Path, input: impl AsRef<OsStr>) -> Result<Vec<Chapter>> {
+    let output = Command::new(ffprobe)
+        .arg("-v")
+        .arg("error")
+        .arg("-show_chapters")
+        .arg("-of")
+        .arg("json")
+        .arg(input)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .context("Failed to run ffprobe")?;
+
+    if !output.status.success() {
+        bail!("ffprobe failed to read chapters");
+    }
+
+    let parsed: FfprobeChapters =
+        serde_json::from_slice(&output.stdout).context("Failed to parse ffprobe chapters")?;
+
+    Ok(parsed
+        .chapters
+        .into_iter()
+        .enumerate()
+        .map(|(i, c)| Chapter {
+            start: c.start_time.parse().unwrap_or(0.0),
+            end: c.end_time.parse().unwrap_or(0.0),
+            title: c.tags.title.unwrap_or_else(|| format!("Chapter {}", i + 1)),
+        })
+        .collect())
+}
+
+/// Read user-supplied markers, one `TIMESTAMP Name` per line (e.g. "1:02:15 Interview").
+///
+/// Each marker runs until the next one; the last runs to the end of the video, which is
+/// left as infinity for the caller to resolve. Blank lines and `#` comments are skipped.
+pub fn from_timestamps_file(path: &Path) -> Result<Vec<Chapter>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read timestamps file {}", path.display()))?;
+
+    let mut markers = Vec::new();
+    for (line_no, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (timestamp, name) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let start = crate::parse_timestamp(timestamp)
+            .with_context(|| format!("{} line {}", path.display(), line_no + 1))?;
+        let title = match name.trim() {
+            "" => format!("Marker {}", markers.len() + 1),
+            name => name.to_string(),
+        };
+        markers.push(Chapter {
+            start,
+            end: f64::INFINITY,
+            title,
+        });
+    }
+
+    if markers.is_empty() {
+        bail!("No timestamps found in {}", path.display());
+    }
+
+    markers.sort_by(|a, b| a.start.total_cmp(&b.start));
+    for i in 1..markers.len() {
+        markers[i - 1].end = markers[i].start;
+    }
+    Ok(markers)
+}
+
+/// Find a chapter by 1-based index or by title (case-insensitive fuzzy match)
+pub fn find_chapter<'a>(chapters: &'a [Chapter], query: &str) -> Result<&'a Chapter> {
+    if chapters.is_empty() {
+        bail!("This video has no chapters. Use --start/--end or --from instead");
+    }
+
+    if let Ok(index) = query.trim().parse::<usize>() {
+        if index == 0 || index > chapters.len() {
+            bail!("Chapter {} is out of range (video has {} chapters)", index, chapters.len());
+        }
+        return Ok(&chapters[index - 1]);
+    }
+
+    let query_lower = query.to_lowercase();
+    let query_words: Vec<&str> = query_lower.split_whitespace().collect();
+
+    // First try: substring match on the title
+    if let Some(chapter) = chapters
+        .iter()
+        .find(|c| c.title.to_lowercase().contains(&query_lower))
+    {
+        return Ok(chapter);
+    }
+
+    // Second try: most query words present (reversed so ties pick the earliest chapter)
+    let best = chapters
+        .iter()
+        .rev()
+        .map(|c| {
+            let title_lower = c.title.to_lowercase();
+            let matches = query_words.iter().filter(|w| title_lower.contains(*w)).count();
+            (c, matches)
+        })
+        .filter(|(_, matches)| *matches > 0)
+        .max_by_key(|(_, matches)| *matches);
+
+    if let Some((chapter, matches)) = best
+        && matches * 2 >= query_words.len()
+    {
+        return Ok(chapter);
+    }
+
+    bail!("Could not find chapter: \"{}\"", query)
+}
@@ -1,25 +1,60 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default, clap::ValueEnum)]
 #[serde(rename_all = "lowercase")]
 pub enum ToolSource {
+    #[default]
     System,
     Managed,
 }
 
-impl Default for ToolSource {
-    fn default() -> Self {
-        Self::System
-    }
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Config {
     #[serde(default)]
     pub tool_source: ToolSource,
+
+    /// Dialogue padding (seconds) for a single --from quote; overridden by --pad flags
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_pad_single: Option<f64>,
+
+    /// Dialogue padding (seconds) for a --from/--to range; overridden by --pad flags
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_pad_range: Option<f64>,
+
+    /// Default -f, comma-separated like the flag; overridden by -f
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_format: Option<String>,
+
+    /// Default --width; overridden by --width
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_width: Option<u32>,
+
+    /// Default --fps; overridden by --fps
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_fps: Option<u32>,
+
+    /// Default subtitle --lang; overridden by --lang
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_lang: Option<String>,
+
+    /// Default --sub-style for burned-in subtitles; overridden by --sub-style
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_sub_style: Option<String>,
+
+    /// Default --output-template for auto-generated filenames
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_template: Option<String>,
+
+    /// OpenSubtitles API key for --fetch-subs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opensubtitles_api_key: Option<String>,
+
+    /// Default --whisper-model for --transcribe
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub whisper_model: Option<String>,
 }
 
 impl Config {
@@ -53,6 +88,81 @@ impl Config {
         Ok(())
     }
 
+    /// Every setting with its current value (None when unset), for `gifclip config show`.
+    /// The API key is masked.
+    pub fn settings(&self) -> Vec<(&'static str, Option<String>)> {
+        let tool_source = match self.tool_source {
+            ToolSource::System => "system",
+            ToolSource::Managed => "managed",
+        };
+        vec![
+            ("tool_source", Some(tool_source.to_string())),
+            ("default_pad_single", self.default_pad_single.map(|p| p.to_string())),
+            ("default_pad_range", self.default_pad_range.map(|p| p.to_string())),
+            ("default_format", self.default_format.clone()),
+            ("default_width", self.default_width.map(|w| w.to_string())),
+            ("default_fps", self.default_fps.map(|f| f.to_string())),
+            ("default_lang", self.default_lang.clone()),
+            ("default_sub_style", self.default_sub_style.clone()),
+            ("output_template", self.output_template.clone()),
+            ("opensubtitles_api_key", self.opensubtitles_api_key.as_ref().map(|_| "(set)".to_string())),
+            ("whisper_model", self.whisper_model.clone()),
+        ]
+    }
+
+    /// Set one setting from its string form, for `gifclip config set`; `None` unsets it
+    pub fn set(&mut self, key: &str, value: Option<&str>) -> Result<()> {
+        let parse_pad = |value: &str| -> Result<f64> {
+            match value.trim().parse::<f64>() {
+                Ok(pad) if pad >= 0.0 => Ok(pad),
+                _ => bail!("{} must be a number of seconds, 0 or more", key),
+            }
+        };
+        let parse_count = |value: &str| -> Result<u32> {
+            match value.trim().parse::<u32>() {
+                Ok(count) if count > 0 => Ok(count),
+                _ => bail!("{} must be a whole number above 0", key),
+            }
+        };
+        let parse_formats = |value: &str| -> Result<String> {
+            crate::parse_formats(value)?;
+            Ok(value.trim().to_string())
+        };
+        let parse_sub_style = |value: &str| crate::parse_sub_style(value).map_err(anyhow::Error::msg);
+        let text = |value: &str| -> Result<String> {
+            if value.trim().is_empty() {
+                bail!("{} can't be empty; use `gifclip config unset {}` to clear it", key, key);
+            }
+            Ok(value.to_string())
+        };
+
+        match key {
+            "tool_source" => {
+                self.tool_source = match value {
+                    Some("system") => ToolSource::System,
+                    Some("managed") => ToolSource::Managed,
+                    Some(other) => bail!("tool_source must be \"system\" or \"managed\", not \"{}\"", other),
+                    None => ToolSource::default(),
+                }
+            }
+            "default_pad_single" => self.default_pad_single = value.map(parse_pad).transpose()?,
+            "default_pad_range" => self.default_pad_range = value.map(parse_pad).transpose()?,
+            "default_format" => self.default_format = value.map(parse_formats).transpose()?,
+            "default_width" => self.default_width = value.map(parse_count).transpose()?,
+            "default_fps" => self.default_fps = value.map(parse_count).transpose()?,
+            "default_lang" => self.default_lang = value.map(text).transpose()?,
+            "default_sub_style" => self.default_sub_style = value.map(parse_sub_style).transpose()?,
+            "output_template" => self.output_template = value.map(text).transpose()?,
+            "opensubtitles_api_key" => self.opensubtitles_api_key = value.map(text).transpose()?,
+            "whisper_model" => self.whisper_model = value.map(text).transpose()?,
+            _ => {
+                let keys: Vec<&str> = self.settings().into_iter().map(|(key, _)| key).collect();
+                bail!("Unknown setting \"{}\" (settings: {})", key, keys.join(", "));
+            }
+        }
+        Ok(())
+    }
+
     pub fn config_dir() -> Result<PathBuf> {
         let home = dirs::home_dir().context("Could not find home directory")?;
         Ok(home.join(".gifclip"))
@@ -66,6 +176,10 @@ impl Config {
         Ok(Self::config_dir()?.join("tools"))
     }
 
+    pub fn cache_dir() -> Result<PathBuf> {
+        Ok(Self::config_dir()?.join("cache"))
+    }
+
     pub fn yt_dlp_path(&self) -> Result<PathBuf> {
         match self.tool_source {
             ToolSource::System => {
@@ -0,0 +1,174 @@
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+use crate::config::Config;
+use crate::failure::{fail, Failure, FailureExt};
+use crate::{build_subtitle_filter, encode_gif, encode_mp4, encode_webm, ffmpeg_lists, setup, srt, Cli, FilterAssets};
+
+/// Filters gifclip relies on, and what goes missing without each
+const FILTERS: &[(&str, &str)] = &[
+    ("subtitles", "burning in subtitles (ffmpeg built without libass)"),
+    ("drawtext", "--text and --burn-timestamp"),
+    ("palettegen", "GIF output"),
+    ("paletteuse", "GIF output"),
+    ("minterpolate", "--smooth"),
+    ("vidstabdetect", "--stabilize"),
+    ("zscale", "--tonemap"),
+];
+
+/// Encoders gifclip relies on, and what goes missing without each
+const ENCODERS: &[(&str, &str)] = &[
+    ("libx264", "MP4 output"),
+    ("libvpx-vp9", "WebM output"),
+    ("libwebp", "WebP output"),
+    ("aac", "MP4 audio"),
+    ("libopus", "WebM audio"),
+];
+
+const SAMPLE_SRT: &str = "1\n00:00:00,200 --> 00:00:01,800\ngifclip doctor\n";
+
+/// One check's outcome, printed as it completes
+struct Report {
+    failures: usize,
+}
+
+impl Report {
+    fn check(&mut self, name: &str, result: Result<String>) {
+        match result {
+            Ok(detail) => println!("  [ok]   {:<16} {}", name, detail),
+            Err(e) => {
+                self.failures += 1;
+                println!("  [FAIL] {:<16} {:#}", name, e);
+            }
+        }
+    }
+}
+
+/// `gifclip doctor`: exercise the whole pipeline on a generated clip, so installation
+/// problems show up without needing a real video
+pub fn run_doctor() -> Result<()> {
+    let config = Config::load()?;
+    let mut report = Report { failures: 0 };
+
+    println!("Tools:");
+    for (name, path) in setup::tool_paths(&config) {
+        let result = path.and_then(|path| match setup::tool_version(name, &path) {
+            Some(version) => Ok(format!("{} ({})", path.display(), version)),
+            None => bail!("{} doesn't run", path.display()),
+        });
+        report.check(name, result);
+    }
+
+    let ffmpeg = match config.ffmpeg_path() {
+        Ok(path) if setup::tool_version("ffmpeg", &path).is_some() => path,
+        _ => fail!(Failure::ToolMissing, "ffmpeg isn't usable; run `gifclip --setup`"),
+    };
+
+    println!("\nCapabilities:");
+    for (filter, needed_for) in FILTERS {
+        report.check(filter, require(&ffmpeg, "-filters", filter, needed_for));
+    }
+    for (encoder, needed_for) in ENCODERS {
+        report.check(encoder, require(&ffmpeg, "-encoders", encoder, needed_for));
+    }
+
+    println!("\nPipeline:");
+    let temp_dir = TempDir::new().context("Failed to create temp directory")?;
+    let temp_path = temp_dir.path();
+
+    let clip = temp_path.join("testsrc.mkv");
+    let generated = generate_test_clip(&ffmpeg, &clip);
+    let generated_ok = generated.is_ok();
+    report.check("test clip", generated.map(|_| "2s testsrc with a tone".to_string()));
+
+    let srt_path = temp_path.join("sample.srt");
+    fs::write(&srt_path, SAMPLE_SRT)?;
+    let parsed = srt::parse_srt(&srt_path).and_then(|entries| match entries.as_slice() {
+        [entry] if entry.text == "gifclip doctor" => Ok(format!("1 cue, {:.1}s-{:.1}s", entry.start, entry.end)),
+        _ => bail!("expected 1 cue, got {}", entries.len()),
+    });
+    report.check("parse srt", parsed);
+
+    let sub_path = Some(srt_path);
+    let filter = build_subtitle_filter(&sub_path, &None, &None, None, false)
+        .context("no subtitle filter built");
+    report.check("subtitle filter", filter);
+
+    if generated_ok {
+        let clip_arg = clip.to_string_lossy().into_owned();
+        let cli = Cli::parse_from(["gifclip", clip_arg.as_str(), "--width", "160", "--fps", "10"]);
+        let assets = FilterAssets {
+            source_start: 0.0,
+            rotation: None,
+            tonemap: false,
+            crop: None,
+            sub_path,
+            soft_subs: None,
+            subs_on_top: false,
+            stabilize_transforms: None,
+            supercut_segments: Vec::new(),
+            speed_ramp_filter: None,
+            audio_filter: None,
+            audio_track: None,
+            cards: None,
+            edge_pad: None,
+        };
+        let encoders: [(&str, &str, EncodeFn); 3] = [
+            ("encode gif", "gif", encode_gif),
+            ("encode mp4", "mp4", encode_mp4),
+            ("encode webm", "webm", encode_webm),
+        ];
+        for (name, extension, encode) in encoders {
+            let output = temp_path.join(format!("doctor.{}", extension));
+            let result = encode(&ffmpeg, &clip, &output, &assets, &cli, 0.0, 2.0)
+                .and_then(|_| output_size(&output));
+            report.check(name, result);
+        }
+    } else {
+        println!("  (skipping encodes without a test clip)");
+    }
+
+    println!();
+    if report.failures > 0 {
+        bail!("{} check(s) failed", report.failures);
+    }
+    println!("All checks passed");
+    Ok(())
+}
+
+type EncodeFn = fn(&Path, &Path, &Path, &FilterAssets, &Cli, f64, f64) -> Result<()>;
+
+/// Whether ffmpeg lists `name` under `flag` (`-filters` or `-encoders`)
+fn require(ffmpeg: &Path, flag: &str, name: &str, needed_for: &str) -> Result<String> {
+    if !ffmpeg_lists(ffmpeg, flag, name) {
+        bail!("missing; needed for {}", needed_for);
+    }
+    Ok("available".to_string())
+}
+
+fn generate_test_clip(ffmpeg: &Path, output: &Path) -> Result<()> {
+    let status = Command::new(ffmpeg)
+        .args(["-y", "-hide_banner", "-loglevel", "error"])
+        .args(["-f", "lavfi", "-i", "testsrc=duration=2:size=320x240:rate=10"])
+        .args(["-f", "lavfi", "-i", "sine=frequency=440:duration=2"])
+        .args(["-shortest"])
+        .arg(output)
+        .status()
+        .context("Failed to run ffmpeg")?;
+    if !status.success() || !output.exists() {
+        bail!("ffmpeg couldn't generate a test clip from its lavfi sources");
+    }
+    Ok(())
+}
+
+fn output_size(path: &Path) -> Result<String> {
+    let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    if size == 0 {
+        bail!("ffmpeg reported success but wrote nothing");
+    }
+    Ok(format!("{} bytes", size))
+}
@@ -0,0 +1,68 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Write the clip's source ranges as a CMX3600 EDL, one video-and-audio event per range,
+/// laid end to end on the record side as a supercut plays them.
+///
+/// Timecodes are non-drop-frame at the source's nominal (rounded) frame rate.
+pub fn write_edl(path: &Path, title: &str, clip_name: &str, segments: &[(f64, f64)], fps: f64) -> Result<()> {
+    let mut out = format!("TITLE: {}\nFCM: NON-DROP FRAME\n\n", title);
+    let mut record = 0.0;
+    for (i, &(start, end)) in segments.iter().enumerate() {
+        let record_end = record + (end - start);
+        out.push_str(&format!(
+            "{:03}  AX       AA/V  C        {} {} {} {}\n* FROM CLIP NAME: {}\n\n",
+            i + 1,
+            timecode(start, fps),
+            timecode(end, fps),
+            timecode(record, fps),
+            timecode(record_end, fps),
+            clip_name
+        ));
+        record = record_end;
+    }
+
+    fs::write(path, out).with_context(|| format!("Failed to write EDL: {}", path.display()))
+}
+
+/// Write the clip's source ranges as an ffmetadata file with one chapter per range, for
+/// `ffmpeg -i video -i FILE -map_metadata 1` or tools that import chapter lists
+pub fn write_ffmetadata(path: &Path, title: &str, segments: &[(f64, f64)]) -> Result<()> {
+    let mut out = format!(";FFMETADATA1\ntitle={}\n", escape_metadata(title));
+    for (i, &(start, end)) in segments.iter().enumerate() {
+        out.push_str(&format!(
+            "\n[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle=Clip {}\n",
+            (start * 1000.0).round() as u64,
+            (end * 1000.0).round() as u64,
+            i + 1
+        ));
+    }
+
+    fs::write(path, out).with_context(|| format!("Failed to write chapters file: {}", path.display()))
+}
+
+/// HH:MM:SS:FF, counting whole frames at the nominal rate (30 for 29.97)
+fn timecode(secs: f64, fps: f64) -> String {
+    let nominal = (fps.round() as u64).max(1);
+    let frames = (secs.max(0.0) * fps).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}:{:02}",
+        frames / nominal / 3600,
+        frames / nominal / 60 % 60,
+        frames / nominal % 60,
+        frames % nominal
+    )
+}
+
+/// ffmetadata treats `=`, `;`, `#`, `\` and newlines as syntax
+fn escape_metadata(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
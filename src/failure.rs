@@ -0,0 +1,74 @@
+use std::error::Error;
+use std::fmt;
+
+/// Failure categories, reported as the process exit code so scripts can tell them apart.
+/// Anything uncategorised exits with 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Failure {
+    /// Bad or conflicting options (clap's own usage errors also exit with 2)
+    Usage = 2,
+    /// ffmpeg, ffprobe or yt-dlp missing or not set up
+    ToolMissing = 3,
+    /// The video or its subtitles couldn't be downloaded
+    Download = 4,
+    /// No subtitles, dialogue, cue, chapter or marker matched
+    NotFound = 5,
+    /// ffmpeg failed to produce the output
+    Encode = 6,
+}
+
+/// An error tagged with its category; it displays exactly like the error it wraps
+struct Categorized {
+    failure: Failure,
+    error: anyhow::Error,
+}
+
+impl fmt::Display for Categorized {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl fmt::Debug for Categorized {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.error, f)
+    }
+}
+
+impl Error for Categorized {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.error.source()
+    }
+}
+
+pub trait FailureExt<T> {
+    /// Tag the error with `failure`, unless an inner step already categorised it
+    fn failure(self, failure: Failure) -> anyhow::Result<T>;
+}
+
+impl<T> FailureExt<T> for anyhow::Result<T> {
+    fn failure(self, failure: Failure) -> anyhow::Result<T> {
+        self.map_err(|error| {
+            if error.is::<Categorized>() {
+                error
+            } else {
+                anyhow::Error::new(Categorized { failure, error })
+            }
+        })
+    }
+}
+
+/// `bail!` with a category
+macro_rules! fail {
+    ($failure:expr, $($arg:tt)*) => {
+        return Err(anyhow::anyhow!($($arg)*)).failure($failure)
+    };
+}
+pub(crate) use fail;
+
+pub fn exit_code(error: &anyhow::Error) -> u8 {
+    error
+        .downcast_ref::<Categorized>()
+        .map(|c| c.failure as u8)
+        .unwrap_or(1)
+}
@@ -0,0 +1,48 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+
+/// Where each input's last clip ended, for --continue
+fn history_path() -> Result<PathBuf> {
+    Ok(Config::cache_dir()?.join("last_clips.json"))
+}
+
+fn load_all() -> HashMap<String, f64> {
+    history_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Local files are keyed by absolute path so relative invocations from other
+/// directories still match; URLs as given
+fn key(input: &str) -> String {
+    let path = Path::new(input);
+    if path.exists()
+        && let Ok(absolute) = path.canonicalize()
+    {
+        return absolute.to_string_lossy().into_owned();
+    }
+    input.to_string()
+}
+
+/// Source time the previous clip of `input` ended at, if any
+pub fn last_clip_end(input: &str) -> Option<f64> {
+    load_all().get(&key(input)).copied()
+}
+
+pub fn record_clip_end(input: &str, end: f64) -> Result<()> {
+    let mut history = load_all();
+    history.insert(key(input), end);
+
+    let path = history_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let json = serde_json::to_string_pretty(&history)?;
+    fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))
+}
@@ -0,0 +1,96 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+use crate::srt::SubtitleEntry;
+
+/// How long the final lyric line stays up, since LRC has no end times
+const LAST_LINE_DURATION: f64 = 5.0;
+
+pub fn parse_lrc(path: &Path) -> Result<Vec<SubtitleEntry>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read subtitle file: {}", path.display()))?;
+
+    Ok(parse_lrc_str(&content))
+}
+
+pub fn parse_lrc_str(content: &str) -> Vec<SubtitleEntry> {
+    // Line format: [mm:ss.xx]text, possibly with several time tags on one line
+    let time_re = Regex::new(r"\[(\d+):(\d{2})(?:[.:](\d{1,3}))?\]").unwrap();
+
+    let mut timed: Vec<(f64, String)> = Vec::new();
+
+    for line in content.lines() {
+        let mut times = Vec::new();
+        let mut rest = line.trim();
+
+        // Leading time tags; metadata tags like [ar:Artist] don't match and are skipped
+        while let Some(caps) = time_re.captures(rest) {
+            let whole = caps.get(0).unwrap();
+            if whole.start() != 0 {
+                break;
+            }
+            times.push(parse_lrc_time(&caps[1], &caps[2], caps.get(3).map(|m| m.as_str())));
+            rest = rest[whole.end()..].trim_start();
+        }
+
+        let text = rest.trim();
+        if text.is_empty() {
+            continue;
+        }
+
+        for time in times {
+            timed.push((time, text.to_string()));
+        }
+    }
+
+    timed.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // Each line lasts until the next one starts
+    let next_starts: Vec<f64> = timed.iter().skip(1).map(|(t, _)| *t).collect();
+    timed
+        .into_iter()
+        .enumerate()
+        .map(|(i, (start, text))| {
+            let end = next_starts.get(i).copied().unwrap_or(start + LAST_LINE_DURATION);
+            SubtitleEntry {
+                index: i + 1,
+                start,
+                end,
+                text,
+            }
+        })
+        .collect()
+}
+
+fn parse_lrc_time(mins: &str, secs: &str, fraction: Option<&str>) -> f64 {
+    let m: f64 = mins.parse().unwrap_or(0.0);
+    let s: f64 = secs.parse().unwrap_or(0.0);
+    // Fractions are hundredths ("xx") or milliseconds ("xxx")
+    let frac: f64 = fraction
+        .map(|f| format!("0.{}", f).parse().unwrap_or(0.0))
+        .unwrap_or(0.0);
+
+    m * 60.0 + s + frac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_last_until_the_next_one() {
+        let entries = parse_lrc_str("[ar:Someone]\n[00:01.50]First\n[00:04.00]Second\n");
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].start, entries[0].end, entries[0].text.as_str()), (1.5, 4.0, "First"));
+        assert_eq!(entries[1].end, 4.0 + LAST_LINE_DURATION);
+    }
+
+    #[test]
+    fn repeated_time_tags_give_one_entry_each() {
+        let entries = parse_lrc_str("[00:10.000][00:02]Chorus\n[00:05]Verse\n");
+        let starts: Vec<(f64, &str)> = entries.iter().map(|e| (e.start, e.text.as_str())).collect();
+        assert_eq!(starts, [(2.0, "Chorus"), (5.0, "Verse"), (10.0, "Chorus")]);
+    }
+}
@@ -1,25 +1,290 @@
+mod ass;
+mod batch;
+mod chapters;
 mod config;
+mod doctor;
+mod edl;
+mod failure;
+mod history;
+mod lrc;
+mod opensubtitles;
+mod probe;
 mod setup;
 mod srt;
+mod transcribe;
+mod trim;
+mod vtt;
 
 use anyhow::{bail, Context, Result};
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
 use regex::Regex;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
+use std::process::{Command, ExitCode, Stdio};
+use std::time::{Duration, Instant};
 use tempfile::TempDir;
+use unicode_segmentation::UnicodeSegmentation;
 
-#[derive(Debug, Clone, ValueEnum, PartialEq)]
+use failure::{fail, Failure, FailureExt};
+use probe::MediaInfo;
+
+/// Output filename (without extension) when neither --output nor a template is given
+const DEFAULT_OUTPUT_TEMPLATE: &str = "{title}_{start}-{end}";
+
+/// Options gifclip sets on its encode commands, which --ffmpeg-arg can't repeat
+const RESERVED_FFMPEG_OPTIONS: &[&str] = &[
+    "-i", "-y", "-n", "-ss", "-sseof", "-t", "-to", "-copyts", "-start_at_zero", "-frames", "-vframes", "-f", "-map",
+    "-filter_complex", "-filter_complex_script", "-lavfi", "-vf", "-af", "-filter", "-c", "-codec", "-vcodec",
+    "-acodec", "-scodec", "-an", "-vn",
+];
+
+/// Default --pix-fmt: 8-bit 4:2:0 plays everywhere
+const DEFAULT_PIX_FMT: &str = "yuv420p";
+
+/// Default --max-duration for GIF and WebP, which get huge and slow well before this
+const DEFAULT_MAX_ANIMATION_DURATION: f64 = 300.0;
+
+/// Largest frame --compat allows: H.264 level 3.0's 1620 macroblocks (720x576)
+const COMPAT_MAX_PIXELS: u32 = 720 * 576;
+
+/// yt-dlp format selectors, tried in order until one downloads
+const YT_DLP_FORMATS: &[&str] = &["b[ext=mp4]/b", "bv*+ba/b", "worst"];
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
 enum OutputFormat {
     Gif,
     Webm,
     Mp4,
+    Webp,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+enum GifProfile {
+    /// Screen recordings and flat UI: full palette, no dithering noise, only changed areas redrawn
+    Crisp,
+    /// Film with gradients: full palette with error-diffusion dithering to hide banding
+    Smooth,
+    /// Smallest file: 64 colors tuned to what moves, light ordered dithering
+    Tiny,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+enum SeekAccuracy {
+    /// Jump straight to the keyframe at or before the start; the clip may begin a little early
+    Fast,
+    /// Jump to a keyframe shortly before the start, then decode to the exact frame
+    Hybrid,
+    /// Decode from the beginning of the source; exact but slow on long or remote sources
+    Accurate,
+}
+
+/// swscale algorithms for --scale-flags
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+enum ScaleFlags {
+    Lanczos,
+    Bicubic,
+    Bilinear,
+    Neighbor,
+    Spline,
+}
+
+/// Video codec for WebM output
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+enum WebmCodec {
+    /// VP9 (libvpx-vp9): smaller files, plays in any current browser
+    Vp9,
+    /// VP8 (libvpx) with Vorbis audio, for old players without VP9
+    Vp8,
+}
+
+impl WebmCodec {
+    /// Display name, video encoder and audio encoder
+    fn encoders(self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            WebmCodec::Vp9 => ("VP9", "libvpx-vp9", "libopus"),
+            WebmCodec::Vp8 => ("VP8", "libvpx", "libvorbis"),
+        }
+    }
+}
+
+/// minterpolate's mi_mode for --smooth
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+enum InterpolationMode {
+    /// Motion-compensated: synthesizes in-between frames along motion vectors
+    Mci,
+    /// Crossfade neighbouring frames; cheap, but ghosts on fast motion
+    Blend,
+    /// Repeat frames, like plain slow-mo
+    Dup,
+}
+
+/// minterpolate's mc_mode for --smooth-mode mci
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+enum CompensationMode {
+    /// Overlapped block motion compensation
+    Obmc,
+    /// Adaptive overlapped block motion compensation; fewer block artifacts
+    Aobmc,
+}
+
+#[derive(Debug, Clone, ValueEnum, PartialEq)]
+enum SubCase {
+    None,
+    Upper,
+    Lower,
+    Title,
+}
+
+#[derive(Debug, Clone, ValueEnum, PartialEq)]
+enum SupercutOrder {
+    Args,
+    Appearance,
+}
+
+/// A `W:H` aspect ratio such as `1:1` or `16:9`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct AspectRatio {
+    width: u32,
+    height: u32,
+}
+
+fn parse_aspect_ratio(s: &str) -> Result<AspectRatio, String> {
+    let (w, h) = s
+        .split_once(':')
+        .ok_or_else(|| format!("Invalid aspect ratio '{}'. Use W:H, e.g. 1:1 or 16:9", s))?;
+    let width: u32 = w.trim().parse().map_err(|_| format!("Invalid aspect ratio width '{}'", w))?;
+    let height: u32 = h.trim().parse().map_err(|_| format!("Invalid aspect ratio height '{}'", h))?;
+    if width == 0 || height == 0 {
+        return Err(format!("Aspect ratio '{}' must have non-zero sides", s));
+    }
+    Ok(AspectRatio { width, height })
+}
+
+/// A `W:H:X:Y` crop rectangle in source pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Crop {
+    width: u32,
+    height: u32,
+    x: u32,
+    y: u32,
+}
+
+fn parse_crop(s: &str) -> Result<Crop, String> {
+    let parts: Vec<u32> = s
+        .split(':')
+        .map(|p| p.trim().parse::<u32>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("Invalid crop '{}'. Use W:H:X:Y in pixels, e.g. 1920:800:0:140", s))?;
+    match parts[..] {
+        [width, height, x, y] if width > 0 && height > 0 => Ok(Crop { width, height, x, y }),
+        _ => Err(format!("Invalid crop '{}'. Use W:H:X:Y in pixels, e.g. 1920:800:0:140", s)),
+    }
+}
+
+/// An RGB color given by name or as hex, e.g. "black" or "#202020"
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rgb(u8, u8, u8);
+
+fn parse_rgb(s: &str) -> Result<Rgb, String> {
+    let named = match s.to_lowercase().as_str() {
+        "black" => Some(Rgb(0, 0, 0)),
+        "white" => Some(Rgb(255, 255, 255)),
+        "gray" | "grey" => Some(Rgb(128, 128, 128)),
+        "red" => Some(Rgb(255, 0, 0)),
+        "green" => Some(Rgb(0, 128, 0)),
+        "blue" => Some(Rgb(0, 0, 255)),
+        "yellow" => Some(Rgb(255, 255, 0)),
+        _ => None,
+    };
+    if let Some(rgb) = named {
+        return Ok(rgb);
+    }
+
+    let hex = s.trim_start_matches('#').trim_start_matches("0x");
+    match u32::from_str_radix(hex, 16) {
+        Ok(n) if hex.len() == 6 => Ok(Rgb((n >> 16) as u8, (n >> 8) as u8, n as u8)),
+        _ => Err(format!("Invalid color '{}'. Use a name (black, white, gray, ...) or hex like #202020", s)),
+    }
+}
+
+fn parse_opacity(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(opacity) if (0.0..=1.0).contains(&opacity) => Ok(opacity),
+        _ => Err(format!("Invalid opacity '{}'. Use 0 (clear) to 1 (solid)", s)),
+    }
+}
+
+fn parse_scene_threshold(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(threshold) if threshold > 0.0 && threshold < 1.0 => Ok(threshold),
+        _ => Err(format!("Invalid scene threshold '{}'. Use a value between 0 and 1, e.g. 0.3", s)),
+    }
+}
+
+fn parse_around(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(seconds) if seconds > 0.0 && seconds.is_finite() => Ok(seconds),
+        _ => Err(format!("Invalid window '{}'. Use a positive number of seconds, e.g. 6", s)),
+    }
+}
+
+/// A comma-separated filter chain spliced into the middle of our own chain, so it can't
+/// start a new graph branch or use stream labels
+fn parse_filter_chain(s: &str) -> Result<String, String> {
+    let chain = s.trim().trim_matches(',').trim();
+    if chain.is_empty() {
+        return Err("Filter chain is empty".to_string());
+    }
+    if let Some(bad) = chain.chars().find(|c| matches!(c, ';' | '[' | ']' | '\n' | '\r')) {
+        return Err(format!(
+            "Invalid character {:?} in filter chain; give plain comma-separated filters, e.g. \"hqdn3d,eq=contrast=1.1\"",
+            bad
+        ));
+    }
+    if chain.chars().filter(|&c| c == '\'').count() % 2 != 0 {
+        return Err("Unbalanced single quote in filter chain".to_string());
+    }
+    Ok(chain.to_string())
+}
+
+fn parse_cue_range(s: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("Invalid cue range '{}'. Use A-B with cue numbers from 1, e.g. 12-15", s);
+    let (a, b) = s.split_once('-').ok_or_else(invalid)?;
+    let a: u32 = a.trim().parse().map_err(|_| invalid())?;
+    let b: u32 = b.trim().parse().map_err(|_| invalid())?;
+    if a == 0 || b < a {
+        return Err(invalid());
+    }
+    Ok((a, b))
+}
+
+fn parse_rotation(s: &str) -> Result<u32, String> {
+    match s.trim() {
+        "0" => Ok(0),
+        "90" => Ok(90),
+        "180" => Ok(180),
+        "270" => Ok(270),
+        _ => Err(format!("Invalid rotation '{}'. Use 0, 90, 180 or 270", s)),
+    }
+}
+
+/// Validate a raw ASS force_style string so it can't break out of the filter's quoting
+fn parse_sub_style(s: &str) -> Result<String, String> {
+    if let Some(bad) = s.chars().find(|c| matches!(c, '\'' | '\\' | ':' | '\n' | '\r')) {
+        return Err(format!(
+            "Invalid character {:?} in subtitle style; use comma-separated Key=Value pairs, e.g. \"Outline=2,Shadow=1\"",
+            bad
+        ));
+    }
+    Ok(s.trim().to_string())
 }
 
 #[derive(Parser)]
 #[command(name = "gifclip")]
 #[command(version)]
+#[command(subcommand_negates_reqs = true)]
+#[command(group(clap::ArgGroup::new("dialogue").multiple(true).args(["from", "cue", "cue_range"])))]
 #[command(about = "Create GIFs/videos with burned-in subtitles from YouTube, local files, or URLs")]
 #[command(long_about = "Create GIFs/videos with burned-in subtitles from YouTube, local files, or URLs.
 
@@ -37,6 +302,9 @@ TIMESTAMP MODE:
     gifclip movie.mp4 --start 1:00
     gifclip video.mp4
 
+  Timestamps are seconds (\"90\"), MM:SS (\"75:30\" = 75 minutes 30 seconds;
+  minutes may exceed 59), or HH:MM:SS (\"1:15:30\").
+
 DIALOGUE MODE:
   gifclip <INPUT> --from \"dialogue text\" [--to \"ending text\"]
 
@@ -48,10 +316,19 @@ DIALOGUE MODE:
   Dialogue range (0.5s default padding):
     gifclip \"URL\" --from \"Here's looking\" --to \"kid\"
 
+  Supercut (several quotes joined into one output):
+    gifclip \"URL\" --from \"first line\" --from \"second line\"
+
   Custom padding:
     gifclip \"URL\" --from \"quote\" --pad 3
     gifclip \"URL\" --from \"quote\" --pad-before 1 --pad-after 5
 
+CHAPTER MODE:
+  gifclip <INPUT> --chapter <NAME|INDEX>
+
+  Clip an entire chapter, chosen by 1-based index or by (fuzzy) title.
+  Use --list-chapters to see the available chapters.
+
 CUSTOM TEXT:
   gifclip <INPUT> [--start <TIME>] [--end <TIME>] --text \"Your caption here\"
 
@@ -61,6 +338,7 @@ INPUT TYPES:
   - YouTube URL: Downloads via yt-dlp, auto-fetches subtitles
   - Local file: Uses embedded subs or looks for matching .srt file
   - Direct URL: Downloads video, uses --subs if provided
+  - HLS stream (.m3u8): Read directly by ffmpeg; live streams need --end or --duration
 
 SETUP:
   gifclip --setup
@@ -75,31 +353,112 @@ struct Cli {
     #[arg(long)]
     setup: bool,
 
+    /// Run setup without prompts: use tools on PATH (system) or download them (managed)
+    #[arg(long, value_enum, value_name = "SOURCE")]
+    setup_source: Option<config::ToolSource>,
+
     /// Input: YouTube URL, local file path, or direct video URL
-    #[arg(required_unless_present = "setup")]
+    #[arg(required_unless_present_any = ["setup", "setup_source", "batch"])]
     input: Option<String>,
 
-    /// Start timestamp (e.g., "1:30" or "00:01:30" or "90")
+    /// Start timestamp: seconds, MM:SS (minutes may exceed 59), or HH:MM:SS (e.g., "90", "75:30", "1:15:30")
     #[arg(long, conflicts_with = "from")]
     start: Option<String>,
 
-    /// End timestamp (e.g., "1:35" or "00:01:35" or "95")
+    /// End timestamp: seconds, MM:SS (minutes may exceed 59), or HH:MM:SS (e.g., "95", "75:35", "1:15:35")
     #[arg(long, conflicts_with = "from")]
     end: Option<String>,
 
+    /// Start where the last clip of this input ended, optionally this many seconds later
+    /// (e.g. "--continue" or "--continue 5")
+    #[arg(
+        long = "continue",
+        value_name = "GAP",
+        num_args = 0..=1,
+        default_missing_value = "0",
+        conflicts_with_all = ["start", "from", "chapter", "clip", "frame_start", "frame_end", "at"]
+    )]
+    continue_after: Option<f64>,
+
+    /// Clip length from --start, as an alternative to --end (same formats as --start)
+    #[arg(long, conflicts_with_all = ["end", "from"])]
+    duration: Option<String>,
+
+    /// Refuse to encode an output longer than this many seconds; 0 for no limit
+    /// (default: 300 for GIF and WebP, no limit for MP4 and WebM)
+    #[arg(long)]
+    max_duration: Option<f64>,
+
     /// External subtitle file path or URL (overrides auto-detected subs)
     #[arg(long)]
     subs: Option<String>,
 
-    /// Starting dialogue text to search for in subtitles (alternative to timestamps)
+    /// Starting dialogue text to search for in subtitles (alternative to timestamps).
+    /// Repeat to build a supercut of several quotes
     #[arg(long, conflicts_with_all = ["start", "end"])]
-    from: Option<String>,
+    from: Vec<String>,
 
-    /// Ending dialogue text (optional - if omitted, clips around --from with padding)
+    /// Ending dialogue text (optional - if omitted, clips around --from with padding).
+    /// With several --from, give one --to per --from
     #[arg(long, requires = "from")]
-    to: Option<String>,
+    to: Vec<String>,
 
-    /// Padding in seconds around dialogue clips (default: 0.5s with --to, 2s without)
+    /// Match accents exactly in dialogue search, so "cafe" no longer finds "café"
+    #[arg(long)]
+    exact_diacritics: bool,
+
+    /// If --from isn't found in the chosen subtitles, search the input's other subtitle
+    /// languages (YouTube tracks, embedded streams, adjacent movie.LANG.srt files)
+    #[arg(long, requires = "from")]
+    search_all_langs: bool,
+
+    /// Order of supercut segments: as given on the command line, or as they appear in the video
+    #[arg(long, value_enum, default_value = "args")]
+    supercut_order: SupercutOrder,
+
+    /// List the input's chapters with their times and exit without encoding
+    #[arg(long)]
+    list_chapters: bool,
+
+    /// Clip a whole chapter, by 1-based index or (fuzzy) title
+    #[arg(long, conflicts_with_all = ["start", "end", "duration", "from"])]
+    chapter: Option<String>,
+
+    /// File of named markers, one "TIMESTAMP Name" per line, for use with --clip
+    #[arg(long, value_name = "FILE", requires = "clip")]
+    timestamps_file: Option<PathBuf>,
+
+    /// Clip from a --timestamps-file marker (1-based index or fuzzy name) to the next one
+    #[arg(long, requires = "timestamps_file", conflicts_with_all = ["start", "end", "duration", "from", "chapter"])]
+    clip: Option<String>,
+
+    /// First frame to include (0-based; alternative to --start)
+    #[arg(long, conflicts_with_all = ["start", "end", "duration", "from", "chapter", "clip"])]
+    frame_start: Option<u64>,
+
+    /// Last frame to include (inclusive; alternative to --end)
+    #[arg(long, conflicts_with_all = ["start", "end", "duration", "from", "chapter", "clip"])]
+    frame_end: Option<u64>,
+
+    /// Clip subtitle cue N (1-based, the number in the SRT file) with dialogue padding
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+        conflicts_with_all = ["start", "end", "duration", "from", "chapter", "clip", "frame_start", "frame_end", "at", "continue_after"]
+    )]
+    cue: Option<u32>,
+
+    /// Clip from the start of cue A to the end of cue B, as "A-B"
+    #[arg(
+        long,
+        value_name = "A-B",
+        value_parser = parse_cue_range,
+        conflicts_with_all = ["cue", "start", "end", "duration", "from", "chapter", "clip", "frame_start", "frame_end", "at", "continue_after"]
+    )]
+    cue_range: Option<(u32, u32)>,
+
+    /// Padding in seconds around dialogue clips (default: 0.5s with --to, 2s without, or the config defaults)
     #[arg(long, conflicts_with_all = ["pad_before", "pad_after"])]
     pad: Option<f64>,
 
@@ -111,18 +470,193 @@ struct Cli {
     #[arg(long)]
     pad_after: Option<f64>,
 
+    /// Clip a window of this many seconds centered on the dialogue (or on --at), instead of padding
+    #[arg(
+        long,
+        value_parser = parse_around,
+        conflicts_with_all = ["pad", "pad_before", "pad_after", "balance_padding"]
+    )]
+    around: Option<f64>,
+
+    /// Timestamp to center an --around window on
+    #[arg(long, requires = "around", conflicts_with_all = ["start", "end", "duration", "from", "chapter", "frame_start", "frame_end"])]
+    at: Option<String>,
+
+    /// If padding is cut short by the start or end of the video, add the missing amount to the other end
+    #[arg(long, requires = "dialogue")]
+    balance_padding: bool,
+
+    /// In dialogue mode, move the padded start/end to the nearest silence (within 1s)
+    #[arg(long, requires = "dialogue")]
+    snap_silence: bool,
+
+    /// Step through frames around the clip to set exact start/end points before encoding
+    #[arg(long)]
+    interactive_trim: bool,
+
+    /// Variable speed within the clip as TIME:SPEED keyframes, e.g. "1.5:0.25,3:1"
+    /// (times in seconds from the clip start; audio is not kept)
+    #[arg(long, value_parser = parse_speed_ramp, conflicts_with = "speed")]
+    speed_ramp: Option<SpeedRamp>,
+
+    /// Play the whole clip at this speed (e.g. 0.5 for half speed, 2 for double)
+    #[arg(long, value_parser = parse_speed)]
+    speed: Option<f64>,
+
+    /// Interpolate new frames when slowing down with --speed or --speed-ramp
+    /// (minterpolate; much slower to encode)
+    #[arg(long)]
+    smooth: bool,
+
+    /// How --smooth makes in-between frames
+    #[arg(long, value_enum, default_value = "mci", requires = "smooth")]
+    smooth_mode: InterpolationMode,
+
+    /// Motion compensation for --smooth-mode mci
+    #[arg(long, value_enum, default_value = "aobmc", requires = "smooth")]
+    smooth_compensation: CompensationMode,
+
+    /// Still image to show before the clip, as IMAGE:SECONDS (e.g. "logo.png:0.5")
+    #[arg(long, value_name = "IMAGE:SECONDS", value_parser = parse_card)]
+    intro: Option<Card>,
+
+    /// Still image to show after the clip, as IMAGE:SECONDS
+    #[arg(long, value_name = "IMAGE:SECONDS", value_parser = parse_card)]
+    outro: Option<Card>,
+
+    /// Embed the frame at this time into the clip (same formats as --start, counted from the
+    /// start of the clip) as the MP4's cover image, for web players' previews
+    #[arg(long, value_name = "TIME")]
+    poster: Option<String>,
+
+    /// Add this many seconds of black frames before and after the clip, for platforms
+    /// that eat the first or last frame
+    #[arg(long, value_name = "SECONDS", conflicts_with_all = ["pad_hold", "audio", "audio_only", "copy_codecs"])]
+    pad_black: Option<f64>,
+
+    /// Like --pad-black, but hold the first and last frames instead of adding black
+    #[arg(long, value_name = "SECONDS", conflicts_with_all = ["audio", "audio_only", "copy_codecs"])]
+    pad_hold: Option<f64>,
+
+    /// Trim without re-encoding when nothing needs filtering (mp4/webm with a matching source
+    /// codec). Keeps the source size and frame rate, and cuts land on keyframes.
+    #[arg(long)]
+    copy_codecs: bool,
+
+    /// Extract just the audio of the clip; the codec follows the -o extension
+    /// (mp3, m4a, opus, ogg, flac, wav; mp3 without -o)
+    #[arg(long, conflicts_with_all = ["audio", "intro", "outro", "speed_ramp", "smooth", "stabilize", "copy_codecs", "smart_sub_position"])]
+    audio_only: bool,
+
+    /// Keep the audio track (mp4/webm only)
+    #[arg(long)]
+    audio: bool,
+
+    /// With --audio or --audio-only, use this audio track (1 is the first) in
+    /// multi-language files
+    #[arg(long, value_name = "N", conflicts_with = "audio_lang", value_parser = clap::value_parser!(u32).range(1..))]
+    audio_track: Option<u32>,
+
+    /// With --audio or --audio-only, use the first audio track tagged with this
+    /// language (e.g. "ja" or "jpn")
+    #[arg(long, value_name = "CODE")]
+    audio_lang: Option<String>,
+
+    /// With --audio or --audio-only, even out loudness to EBU R128 (two-pass loudnorm)
+    #[arg(long)]
+    normalize_audio: bool,
+
+    /// With --speed and --audio, change tempo without changing pitch
+    /// (rubberband if ffmpeg has it, otherwise atempo)
+    #[arg(long, requires = "speed")]
+    preserve_pitch: bool,
+
+    /// Nudge start/end (up to 0.5s each) to the most similar frames for a seamless loop
+    #[arg(long)]
+    seamless: bool,
+
+    /// Split the clip at scene cuts and write one output per scene, numbered
+    #[arg(long, conflicts_with_all = ["seamless", "interactive_trim", "export_subs", "export_edl"])]
+    split_scenes: bool,
+
+    /// How different consecutive frames must be to count as a scene cut for
+    /// --split-scenes (0-1; lower finds more cuts)
+    #[arg(
+        long,
+        value_name = "THRESHOLD",
+        default_value = "0.3",
+        value_parser = parse_scene_threshold,
+        requires = "split_scenes"
+    )]
+    scene_threshold: f64,
+
     /// Output filename (auto-generated from video title if not specified)
     #[arg(short, long)]
     output: Option<PathBuf>,
 
-    /// Output format
-    #[arg(short, long, value_enum, default_value = "gif")]
-    format: OutputFormat,
+    /// Title to use instead of the detected video title (for auto-generated filenames)
+    #[arg(long)]
+    title: Option<String>,
+
+    /// Template for auto-generated output names; the extension is added automatically.
+    /// Placeholders: {title} {start} {end} {duration} {width} {fps} {format} {index}
+    #[arg(long, conflicts_with = "output")]
+    output_template: Option<String>,
+
+    /// Output format. Comma-separate or repeat for one output per format from a single
+    /// download, e.g. `-f gif,mp4`
+    #[arg(short = 'f', long = "format", value_enum, value_delimiter = ',', default_value = "gif")]
+    formats: Vec<OutputFormat>,
 
     /// Width in pixels (height scales proportionally)
     #[arg(short, long, default_value = "480")]
     width: u32,
 
+    /// How to seek to the clip: fast (keyframe-aligned start), hybrid (keyframe jump, then
+    /// exact), or accurate (decode from the start of the source). --stabilize always uses accurate
+    #[arg(long, value_enum, default_value = "hybrid")]
+    seek_accuracy: SeekAccuracy,
+
+    /// Pixel format for mp4/webm output (e.g. yuv420p, yuv444p, yuv420p10le)
+    #[arg(long, value_name = "FMT", default_value = DEFAULT_PIX_FMT)]
+    pix_fmt: String,
+
+    /// Video codec for WebM output
+    #[arg(long, value_enum, default_value = "vp9")]
+    webm_codec: WebmCodec,
+
+    /// MP4 that plays everywhere (old phones, embedded players): H.264 Baseline level 3.0,
+    /// yuv420p, even dimensions of at most 720x576 worth of pixels. Bigger files for the
+    /// same quality, since Baseline drops B-frames and CABAC.
+    #[arg(long, conflicts_with_all = ["pix_fmt", "copy_codecs"])]
+    compat: bool,
+
+    /// Tone-map HDR sources to SDR so the clip isn't washed out (needs ffmpeg with zimg)
+    #[arg(long)]
+    tonemap: bool,
+
+    /// Turn the source clockwise by this many degrees (0, 90, 180 or 270), replacing any
+    /// rotation the file asks for; 0 ignores the file's rotation
+    #[arg(long, value_name = "DEGREES", value_parser = parse_rotation)]
+    rotate: Option<u32>,
+
+    /// Crop the source to W:H:X:Y (pixels) before scaling
+    #[arg(long, value_parser = parse_crop)]
+    crop: Option<Crop>,
+
+    /// Detect black letterbox/pillarbox bars and crop them off (--crop takes precedence)
+    #[arg(long)]
+    trim_black: bool,
+
+    /// Size by height in pixels instead of width (handy for portrait sources)
+    #[arg(long, conflicts_with = "blur_pad")]
+    height: Option<u32>,
+
+    /// Scaling algorithm (default: lanczos for gif, bilinear for other formats;
+    /// neighbor keeps pixel art crisp)
+    #[arg(long, value_enum, value_name = "ALGO")]
+    scale_flags: Option<ScaleFlags>,
+
     /// Frames per second
     #[arg(long, default_value = "15")]
     fps: u32,
@@ -131,457 +665,3802 @@ struct Cli {
     #[arg(long, default_value = "en")]
     lang: String,
 
+    /// Subtitle file character encoding (e.g. "windows-1252", "shift_jis"), or "auto" to detect
+    #[arg(long, default_value = "auto")]
+    sub_encoding: String,
+
     /// Skip subtitles
     #[arg(long)]
     no_subs: bool,
 
-    /// Custom text to overlay on the clip (displayed for entire duration)
-    #[arg(long)]
-    text: Option<String>,
+    /// For mp4/webm, add subtitles as a track viewers can toggle instead of burning them in
+    #[arg(long, overrides_with = "burn_subs")]
+    soft_subs: bool,
 
-    /// Quality for lossy formats (1-100, higher is better). For gif, reduces colors.
-    #[arg(short, long, default_value = "80")]
-    quality: u32,
-}
+    /// Burn subtitles into the picture (the default; overrides an earlier --soft-subs)
+    #[arg(long, overrides_with = "soft_subs")]
+    burn_subs: bool,
 
-#[derive(Subcommand)]
-enum Commands {
-    /// Configure gifclip (tool sources, etc.)
-    Setup,
-}
+    /// Never use YouTube auto-generated captions (manual subtitles only)
+    #[arg(long, conflicts_with = "prefer_manual")]
+    no_auto_sub: bool,
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+    /// Try manual YouTube subtitles first, falling back to auto-captions with a warning
+    #[arg(long)]
+    prefer_manual: bool,
 
-    // Handle setup flag or subcommand
-    if cli.setup || matches!(cli.command, Some(Commands::Setup)) {
-        setup::run_setup()?;
-        return Ok(());
-    }
+    /// Download matching subtitles from OpenSubtitles when a file has none (needs an API key)
+    #[arg(long)]
+    fetch_subs: bool,
 
-    // Ensure tools are configured
-    let config = setup::ensure_setup()?;
+    /// Generate subtitles with whisper when the input has none (whisper.cpp or openai-whisper)
+    #[arg(long, conflicts_with = "no_subs")]
+    transcribe: bool,
 
-    let temp_dir = TempDir::new().context("Failed to create temp directory")?;
-    let temp_path = temp_dir.path();
+    /// Whisper model: a ggml file path for whisper.cpp, or a model name for openai-whisper
+    #[arg(long, value_name = "MODEL")]
+    whisper_model: Option<String>,
 
-    let ffmpeg = config.ffmpeg_path()?;
+    /// Re-download YouTube subtitles even if a cached copy exists
+    #[arg(long)]
+    refresh_subs: bool,
 
-    let input = cli.input.as_ref().context("Input is required")?;
+    /// Reuse what ffprobe found in a local source on earlier runs (until the file changes).
+    /// Useful on batch lines that clip the same source
+    #[arg(long)]
+    probe_cache: bool,
 
-    // Skip subtitle handling if custom text is provided
-    let skip_subs = cli.no_subs || cli.text.is_some();
+    /// Pad the output to an aspect ratio (e.g. "1:1" or "16:9"), centering the content
+    #[arg(long, value_parser = parse_aspect_ratio)]
+    pad_to: Option<AspectRatio>,
 
-    // Determine input type and get video + subtitles
-    let (video_path, video_title, sub_path) = if is_url(input) && is_youtube_url(input) {
-        // YouTube mode - use yt-dlp
-        let yt_dlp = config.yt_dlp_path()?;
+    /// Background color for --pad-to (ffmpeg color name or hex, e.g. "white" or "#202020")
+    #[arg(long, default_value = "black", requires = "pad_to")]
+    pad_color: String,
 
-        let video_title = get_video_title(&yt_dlp, input)?;
-        println!("Video: {}", video_title);
+    /// Fill to an aspect ratio (e.g. "9:16") with a blurred, zoomed copy of the clip instead of solid bars
+    #[arg(long, value_parser = parse_aspect_ratio, conflicts_with = "pad_to")]
+    blur_pad: Option<AspectRatio>,
 
-        // Download video (always get subs for dialogue mode, or if user wants them)
-        let need_subs = cli.subs.is_none() && (cli.from.is_some() || !skip_subs);
+    /// Round the output's corners to this radius in pixels (true transparency for webm only)
+    #[arg(long)]
+    rounded_corners: Option<u32>,
 
-        println!("Downloading video...");
-        let video_path = temp_path.join("video.mp4");
-        let mut dl_cmd = Command::new(&yt_dlp);
-        dl_cmd
-            .arg("-f")
-            .arg("b[ext=mp4]/b")
-            .arg("-o")
-            .arg(&video_path)
-            .arg("--no-playlist");
+    /// Fill color for rounded corners in gif/mp4 output, which can't carry alpha
+    #[arg(long, default_value = "black", requires = "rounded_corners")]
+    corner_color: String,
 
-        if need_subs {
-            dl_cmd
-                .arg("--write-sub")
-                .arg("--write-auto-sub")
-                .arg("--sub-lang")
-                .arg(&cli.lang)
-                .arg("--convert-subs")
-                .arg("srt");
-        }
+    /// Draw the running source timestamp in the corner (for checking cut points)
+    #[arg(long)]
+    burn_timestamp: bool,
 
-        dl_cmd.arg(input);
+    /// Raw ffmpeg filters run on the source frames before cropping and subtitles
+    /// (e.g. "hqdn3d"); mistakes show up as ffmpeg errors
+    #[arg(long, value_name = "FILTERS", value_parser = parse_filter_chain)]
+    vf_prepend: Option<String>,
 
-        let dl_status = dl_cmd.status().context("Failed to run yt-dlp")?;
-        if !dl_status.success() {
-            bail!("yt-dlp failed to download video");
-        }
+    /// Raw ffmpeg filters appended to the end of the video filter chain
+    /// (e.g. "tblend=all_mode=average"); mistakes show up as ffmpeg errors
+    #[arg(long, value_name = "FILTERS", value_parser = parse_filter_chain)]
+    vf_append: Option<String>,
 
-        // Handle subtitles
-        let sub_path = if let Some(ref subs_input) = cli.subs {
-            Some(resolve_subs_input(subs_input, temp_path)?)
-        } else {
-            find_subtitle_file(temp_path, &cli.lang)
-        };
+    /// Raw ffmpeg option passed right after the program name when encoding, one word per
+    /// flag: `--ffmpeg-arg -probesize --ffmpeg-arg 50M`. Options gifclip sets itself are
+    /// rejected; anything else is passed through unchecked and can break the encode
+    #[arg(long, value_name = "ARG", allow_hyphen_values = true)]
+    ffmpeg_arg: Vec<String>,
 
-        (video_path, video_title, sub_path)
-    } else if is_url(input) {
-        // Direct URL mode - download video, check embedded subs only
-        println!("Downloading video...");
-        let ext = Path::new(input)
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("mp4");
-        let video_path = temp_path.join(format!("video.{}", ext));
-        download_file(input, &video_path)?;
+    /// Make this color transparent (e.g. "green" or "0x00FF00"), for stickers/overlays (gif and webm)
+    #[arg(long)]
+    chroma_key: Option<String>,
 
-        let video_title = get_filename_from_url(input);
-        println!("Video: {}", video_title);
+    /// How close a color must be to --chroma-key to be keyed out (0.01-1.0)
+    #[arg(long, default_value = "0.1", requires = "chroma_key")]
+    chroma_similarity: f64,
 
-        // Handle subtitles - explicit subs or try embedded
-        let sub_path = if let Some(ref subs_input) = cli.subs {
-            Some(resolve_subs_input(subs_input, temp_path)?)
-        } else if !skip_subs {
-            let extracted_subs = temp_path.join("extracted.srt");
-            if extract_embedded_subs(&ffmpeg, &video_path, &extracted_subs)? {
-                println!("Extracted embedded subtitles");
-                Some(extracted_subs)
-            } else {
-                None
-            }
-        } else {
-            None
-        };
+    /// Edge softness for --chroma-key (0.0 = hard edge; gif ignores partial alpha)
+    #[arg(long, default_value = "0.0", requires = "chroma_key")]
+    chroma_blend: f64,
 
-        (video_path, video_title, sub_path)
-    } else {
-        // Local file mode - check embedded subs, then adjacent .srt
-        let video_path = PathBuf::from(input);
-        if !video_path.exists() {
-            bail!("Input file does not exist: {}", input);
-        }
+    /// Move subtitles to the top when the bottom of the clip is bright or busy
+    /// (burned-in captions, credits)
+    #[arg(long)]
+    smart_sub_position: bool,
 
-        let video_title = get_filename_from_path(input);
-        println!("Video: {}", video_title);
+    /// Stabilize shaky footage (two-pass vidstab; needs ffmpeg with libvidstab)
+    #[arg(long)]
+    stabilize: bool,
 
-        // Handle subtitles - explicit, embedded, or adjacent file
-        let sub_path = if let Some(ref subs_input) = cli.subs {
-            Some(resolve_subs_input(subs_input, temp_path)?)
-        } else if !skip_subs {
-            // First try embedded subs
-            let extracted_subs = temp_path.join("extracted.srt");
-            if extract_embedded_subs(&ffmpeg, &video_path, &extracted_subs)? {
-                println!("Extracted embedded subtitles");
-                Some(extracted_subs)
-            } else {
-                // Look for adjacent subtitle file with same name
-                find_adjacent_subtitle(&video_path)
-            }
-        } else {
-            None
-        };
+    /// Custom text to overlay on the clip (displayed for entire duration)
+    #[arg(long)]
+    text: Option<String>,
 
-        (video_path, video_title, sub_path)
-    };
+    /// Rewrap burned-in subtitle lines to at most this many characters
+    #[arg(long, value_name = "CHARS", value_parser = clap::value_parser!(u32).range(1..))]
+    sub_max_chars: Option<u32>,
 
-    // Determine start/end times
-    let (start_secs, end_secs) = if let Some(ref from_text) = cli.from {
-        // Dialogue mode - search subtitles
-        let sub_file = sub_path.as_ref()
-            .context("Subtitles required for dialogue search but none found")?;
+    /// Change the case of burned-in subtitle text
+    #[arg(long, value_enum, default_value = "none")]
+    sub_case: SubCase,
 
-        let entries = srt::parse_srt(sub_file)?;
+    /// Raw ASS style overrides for burned-in subtitles (e.g. "Outline=2,Shadow=1,Bold=1")
+    #[arg(long, value_name = "STYLE", value_parser = parse_sub_style)]
+    sub_style: Option<String>,
 
-        let from_entry = srt::find_dialogue(&entries, from_text)
-            .with_context(|| format!("Could not find starting dialogue: \"{}\"", from_text))?;
+    /// Draw a box behind burned-in subtitles (and --text) for readability over bright scenes
+    #[arg(long)]
+    sub_box: bool,
+
+    /// Color of the --sub-box background (name or hex, e.g. "black" or "#202020")
+    #[arg(long, value_name = "COLOR", default_value = "black", value_parser = parse_rgb, requires = "sub_box")]
+    sub_box_color: Rgb,
+
+    /// Opacity of the --sub-box background, from 0 (clear) to 1 (solid)
+    #[arg(long, value_name = "OPACITY", default_value = "0.6", value_parser = parse_opacity, requires = "sub_box")]
+    sub_box_opacity: f64,
+
+    /// Save the subtitle file used for the clip (after any download, extraction or rewrite)
+    #[arg(long, value_name = "PATH")]
+    keep_subs: Option<PathBuf>,
+
+    /// Write the clip's subtitles (trimmed, retimed to start at 0, with --sub-case and
+    /// --sub-max-chars applied) to PATH as .srt or .vtt, without encoding anything
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["no_subs", "text", "audio_only", "speed", "speed_ramp", "intro", "outro", "pad_black", "pad_hold"]
+    )]
+    export_subs: Option<PathBuf>,
+
+    /// Write the clip's source in/out points to PATH instead of encoding: a CMX3600 EDL
+    /// for .edl, otherwise an ffmetadata chapters file
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["speed", "speed_ramp", "intro", "outro", "pad_black", "pad_hold"])]
+    export_edl: Option<PathBuf>,
+
+    /// GIF palette size (2-256); overrides the palette size derived from --quality
+    #[arg(long, visible_alias = "palette-colors", value_parser = clap::value_parser!(u32).range(2..=256))]
+    colors: Option<u32>,
+
+    /// Curated GIF palette, dithering and scaling settings; --colors and --scale-flags
+    /// still override it
+    #[arg(long, value_enum, value_name = "PROFILE")]
+    gif_profile: Option<GifProfile>,
+
+    /// Build the GIF palette from a sample of frames first, then encode without holding
+    /// the whole clip in memory. Slightly less accurate colors, and the clip is decoded and
+    /// filtered twice, so --stabilize, --smooth and burned subtitles also run twice
+    #[arg(long)]
+    single_pass_palette: bool,
 
-        let (start, end, default_pad) = if let Some(ref to_text) = cli.to {
-            // Range mode: from dialogue to dialogue
-            let to_entry = srt::find_dialogue(&entries, to_text)
-                .with_context(|| format!("Could not find ending dialogue: \"{}\"", to_text))?;
+    /// Print how long each phase (download, subtitles, clip selection, encode) took
+    #[arg(long)]
+    stats: bool,
 
-            if to_entry.end < from_entry.start {
-                bail!("Ending dialogue appears before starting dialogue");
-            }
+    /// Don't print the output summary (dimensions, frames, size) after encoding, and
+    /// silence yt-dlp's download progress
+    #[arg(long)]
+    quiet: bool,
 
-            (from_entry.start, to_entry.end, 0.5)
-        } else {
-            // Single quote mode: just the one subtitle entry
-            (from_entry.start, from_entry.end, 2.0)
-        };
+    /// Encode every clip listed in FILE (`-` for stdin), one set of gifclip arguments per line
+    #[arg(long, value_name = "FILE", conflicts_with = "input")]
+    batch: Option<PathBuf>,
 
-        let pad_before = cli.pad_before.or(cli.pad).unwrap_or(default_pad);
-        let pad_after = cli.pad_after.or(cli.pad).unwrap_or(default_pad);
-        let start_padded = (start - pad_before).max(0.0);
-        let end_padded = end + pad_after;
+    /// Maximum number of clips to encode at once in --batch mode
+    #[arg(long, value_name = "N", default_value = "2", value_parser = clap::value_parser!(u32).range(1..))]
+    jobs: u32,
 
-        println!(
-            "Found dialogue at {:.1}s - {:.1}s (padding: {:.1}s before, {:.1}s after)",
-            start, end, pad_before, pad_after
-        );
+    /// Position of this clip within a batch, used for the {index} placeholder
+    #[arg(long, hide = true, default_value = "1")]
+    batch_index: usize,
 
-        (start_padded, end_padded)
-    } else {
-        // Timestamp mode - handle optional start/end
-        let start_secs = if let Some(ref start) = cli.start {
-            parse_timestamp(start)?
-        } else {
-            0.0
-        };
+    /// Quality for lossy formats (1-100, higher is better). For gif, reduces colors.
+    #[arg(short, long, default_value = "80")]
+    quality: u32,
 
-        let end_secs = if let Some(ref end) = cli.end {
-            parse_timestamp(end)?
-        } else {
-            // Get video duration
-            get_video_duration(&config, &video_path)?
-        };
+    /// Encode WebP losslessly (--quality is ignored)
+    #[arg(long)]
+    lossless: bool,
 
-        if end_secs <= start_secs {
-            bail!("End time must be after start time");
-        }
+    /// WebP compression effort (0-6): higher is smaller but slower to encode
+    #[arg(long, default_value = "4", value_parser = clap::value_parser!(u32).range(0..=6))]
+    compression_level: u32,
+}
 
+#[derive(Subcommand)]
+enum Commands {
+    /// Configure gifclip (tool sources, etc.)
+    Setup,
+
+    /// Show every subtitle line a dialogue query matches, by strategy, without encoding
+    Match {
+        /// Input: YouTube URL, local file path, or direct video URL
+        input: String,
+
+        /// Dialogue text to look for, as you would pass to --from
+        query: String,
+
+        /// External subtitle file or URL
+        #[arg(long)]
+        subs: Option<String>,
+
+        /// Subtitle language code
+        #[arg(long, default_value = "en")]
+        lang: String,
+
+        /// Match accents exactly, as with --exact-diacritics
+        #[arg(long)]
+        exact_diacritics: bool,
+    },
+
+    /// Check the installation by clipping a generated test video to every format
+    Doctor,
+
+    /// Inspect gifclip's configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print the settings and where each tool resolves to (and whether it runs)
+    Show {
+        /// Print as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Change a setting without the interactive setup (e.g. `config set tool_source system`)
+    Set {
+        /// Setting name, as listed by `config show`
+        key: String,
+
+        #[arg(allow_hyphen_values = true)]
+        value: String,
+    },
+
+    /// Reset a setting to its default
+    Unset {
+        /// Setting name, as listed by `config show`
+        key: String,
+    },
+}
+
+/// `gifclip config set/unset`: change one setting and save
+fn update_config(key: &str, value: Option<&str>) -> Result<()> {
+    let mut config = config::Config::load()?;
+    config.set(key, value).failure(Failure::Usage)?;
+    config.save()?;
+
+    match value {
+        // Keep the key out of the terminal scrollback
+        Some(_) if key == "opensubtitles_api_key" => println!("Set {}", key),
+        Some(value) => println!("Set {} = {}", key, value),
+        None => println!("Unset {}", key),
+    }
+    Ok(())
+}
+
+/// A comma-separated format list, as given to -f
+fn parse_formats(value: &str) -> Result<Vec<OutputFormat>> {
+    value
+        .split(',')
+        .map(|format| {
+            OutputFormat::from_str(format.trim(), true).map_err(|_| {
+                let names: Vec<String> = OutputFormat::value_variants()
+                    .iter()
+                    .filter_map(|v| v.to_possible_value().map(|v| v.get_name().to_string()))
+                    .collect();
+                anyhow::anyhow!("Unknown format \"{}\" (formats: {})", format.trim(), names.join(", "))
+            })
+        })
+        .collect()
+}
+
+/// Take the defaults saved with `gifclip config set` for the options not given on the
+/// command line
+fn apply_config_defaults(cli: &mut Cli, matches: &ArgMatches, config: &config::Config) -> Result<()> {
+    let unset = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+
+    if let Some(ref formats) = config.default_format
+        && unset("formats")
+    {
+        cli.formats = parse_formats(formats).context("Invalid default_format in the config")?;
+    }
+    if let Some(width) = config.default_width
+        && unset("width")
+    {
+        cli.width = width;
+    }
+    if let Some(fps) = config.default_fps
+        && unset("fps")
+    {
+        cli.fps = fps;
+    }
+    if let Some(ref lang) = config.default_lang
+        && unset("lang")
+    {
+        cli.lang = lang.clone();
+    }
+    if let Some(ref style) = config.default_sub_style
+        && unset("sub_style")
+    {
+        cli.sub_style = Some(style.clone());
+    }
+    Ok(())
+}
+
+/// Option combinations clap can't check on its own
+fn validate_args(cli: &Cli) -> Result<()> {
+    // With several formats, options for one format apply to that output only
+    let has_format = |format: OutputFormat| cli.formats.contains(&format);
+
+    if cli.audio_only && cli.formats.len() > 1 {
+        bail!("--audio-only writes a single audio file; give at most one -f");
+    }
+
+    if cli.chroma_key.is_some() && has_format(OutputFormat::Mp4) {
+        bail!("MP4 can't carry transparency; use -f webm or -f gif with --chroma-key");
+    }
+
+    if cli.audio && !has_format(OutputFormat::Mp4) && !has_format(OutputFormat::Webm) {
+        bail!("{:?} has no audio; use -f mp4 or -f webm with --audio", cli.formats[0]);
+    }
+
+    if cli.poster.is_some() && (!has_format(OutputFormat::Mp4) || cli.audio_only) {
+        bail!("--poster sets an MP4 cover image; use it with -f mp4");
+    }
+
+    if cli.compat && (!has_format(OutputFormat::Mp4) || cli.audio_only) {
+        bail!("--compat makes a widely playable MP4; use it with -f mp4");
+    }
+
+    if cli.normalize_audio && !cli.audio && !cli.audio_only {
+        bail!("--normalize-audio works on kept audio; add --audio (mp4/webm) or --audio-only");
+    }
+
+    if (cli.audio_track.is_some() || cli.audio_lang.is_some()) && !cli.audio && !cli.audio_only {
+        bail!("--audio-track and --audio-lang pick the track for --audio or --audio-only; add one of those");
+    }
+
+    for card in cli.intro.iter().chain(&cli.outro) {
+        if !card.image.exists() {
+            bail!("Card image does not exist: {}", card.image.display());
+        }
+    }
+
+    if cli.audio && (cli.intro.is_some() || cli.outro.is_some()) {
+        bail!("--intro/--outro cards have no sound; they can't be combined with --audio");
+    }
+
+    if cli.audio && cli.speed_ramp.is_some() {
+        bail!("--speed-ramp can't keep audio; use a constant --speed with --audio");
+    }
+
+    if cli.smooth {
+        let slows = cli.speed.map(|s| s < 1.0).unwrap_or(false)
+            || cli.speed_ramp.as_ref().is_some_and(|r| r.keyframes.iter().any(|&(_, s)| s < 1.0));
+        if cli.speed.is_none() && cli.speed_ramp.is_none() {
+            bail!("--smooth interpolates slow motion; add --speed (e.g. 0.5) or --speed-ramp");
+        }
+        if !slows {
+            eprintln!("Note: --smooth only adds frames where the clip is slowed down (speed below 1)");
+        } else {
+            eprintln!("Note: --smooth estimates motion for every frame and can take many times longer to encode");
+        }
+    }
+
+    if cli.gif_profile.is_some() && !has_format(OutputFormat::Gif) {
+        bail!("--gif-profile only applies to GIF output");
+    }
+
+    if cli.single_pass_palette && !has_format(OutputFormat::Gif) {
+        bail!("--single-pass-palette only applies to GIF output");
+    }
+
+    if cli.lossless && !has_format(OutputFormat::Webp) {
+        bail!("--lossless only applies to WebP output; add -f webp");
+    }
+
+    if let Some(ref path) = cli.export_subs
+        && subtitle_extension(&path.to_string_lossy()).is_none_or(|ext| ext != "srt" && ext != "vtt")
+    {
+        bail!("--export-subs writes SRT or VTT; give a path ending in .srt or .vtt");
+    }
+
+    // Stream specifiers don't make an option different ("-c:v" is still "-c")
+    if let Some(arg) = cli
+        .ffmpeg_arg
+        .iter()
+        .find(|arg| RESERVED_FFMPEG_OPTIONS.contains(&arg.split(':').next().unwrap_or(arg)))
+    {
+        bail!("--ffmpeg-arg {} would clash with the inputs, timing or encoding gifclip sets up", arg);
+    }
+
+    let subtitle_search = !cli.from.is_empty() || cli.cue.is_some() || cli.cue_range.is_some();
+    if cli.around.is_some() && !subtitle_search && cli.at.is_none() {
+        bail!("--around needs --from or --cue (dialogue) or --at (timestamp) to center on");
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    match run(cli, &matches) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            ExitCode::from(failure::exit_code(&e))
+        }
+    }
+}
+
+fn run(mut cli: Cli, matches: &ArgMatches) -> Result<()> {
+    // Handle setup flag or subcommand
+    if cli.setup || cli.setup_source.is_some() || matches!(cli.command, Some(Commands::Setup)) {
+        setup::run_setup(cli.setup_source.clone()).failure(Failure::ToolMissing)?;
+        return Ok(());
+    }
+
+    if let Some(Commands::Config { action }) = &cli.command {
+        return match action {
+            ConfigCommand::Show { json } => setup::show_config(*json),
+            ConfigCommand::Set { key, value } => update_config(key, Some(value)),
+            ConfigCommand::Unset { key } => update_config(key, None),
+        };
+    }
+
+    if matches!(cli.command, Some(Commands::Doctor)) {
+        return doctor::run_doctor();
+    }
+
+    apply_config_defaults(&mut cli, matches, &config::Config::load()?).failure(Failure::Usage)?;
+
+    // A repeated format would only overwrite its own output
+    let mut formats: Vec<OutputFormat> = Vec::new();
+    for format in cli.formats.drain(..) {
+        if !formats.contains(&format) {
+            formats.push(format);
+        }
+    }
+    cli.formats = formats;
+
+    validate_args(&cli).failure(Failure::Usage)?;
+    let subtitle_search = !cli.from.is_empty() || cli.cue.is_some() || cli.cue_range.is_some();
+
+    if let Some(batch) = &cli.batch {
+        // Set up tools once up front; the per-clip processes can't prompt
+        setup::ensure_setup().failure(Failure::ToolMissing)?;
+        return batch::run_batch(batch, cli.jobs as usize);
+    }
+
+    // Ensure tools are configured
+    let config = setup::ensure_setup().failure(Failure::ToolMissing)?;
+
+    if let Some(Commands::Match { input, query, subs, lang, exact_diacritics }) = &cli.command {
+        return run_match(&config, input, query, subs.as_deref(), lang, *exact_diacritics);
+    }
+
+    let temp_dir = TempDir::new().context("Failed to create temp directory")?;
+    let temp_path = temp_dir.path();
+
+    let ffmpeg = config.ffmpeg_path().failure(Failure::ToolMissing)?;
+
+    let input = cli.input.as_ref().context("Input is required")?;
+
+    let mut stats = Stats::new();
+
+    if cli.list_chapters {
+        if !is_url(input) && !Path::new(input).exists() {
+            fail!(Failure::Usage, "Input file does not exist: {}", input);
+        }
+        let chapters = load_chapters(&config, input, Path::new(input))?;
+        if chapters.is_empty() {
+            println!("No chapters found");
+        }
+        for (i, chapter) in chapters.iter().enumerate() {
+            println!(
+                "{:>3}. {} - {}  {}",
+                i + 1,
+                format_clock(chapter.start),
+                format_clock(chapter.end),
+                chapter.title
+            );
+        }
+        return Ok(());
+    }
+
+    // Skip subtitle handling if custom text is provided
+    let skip_subs = cli.no_subs || cli.text.is_some() || cli.audio_only;
+
+    // Determine input type and get video + subtitles
+    let (video_path, video_title, sub_path, media) = if is_url(input) && is_youtube_url(input) {
+        // YouTube mode - use yt-dlp
+        let yt_dlp = config.yt_dlp_path().failure(Failure::ToolMissing)?;
+
+        let video_title = get_video_title(&yt_dlp, input)?;
+        println!("Video: {}", video_title);
+
+        // Download video (always get subs for dialogue mode, or if user wants them)
+        let need_subs = cli.subs.is_none() && (subtitle_search || !skip_subs);
+
+        // Reuse subtitles from an earlier run on the same video instead of fetching them again
+        let sub_cache = if need_subs { subtitle_cache_path(input, &cli) } else { None };
+        let cached_subs = match &sub_cache {
+            Some(cache) if cache.exists() && !cli.refresh_subs => {
+                let dest = temp_path.join(format!("video.{}.srt", cli.lang));
+                fs::copy(cache, &dest).context("Failed to copy cached subtitles")?;
+                println!("Using cached subtitles: {}", cache.display());
+                true
+            }
+            _ => false,
+        };
+        let fetch_subs = need_subs && !cached_subs;
+
+        println!("Downloading video...");
+        let video_path = temp_path.join("video.mp4");
+
+        // Start with the fast single-file mp4 selector, loosening it if the video lacks one
+        let mut downloaded = false;
+        for (i, selector) in YT_DLP_FORMATS.iter().enumerate() {
+            if i > 0 {
+                println!("Retrying download with format \"{}\"...", selector);
+            }
+
+            let mut dl_cmd = Command::new(&yt_dlp);
+            dl_cmd
+                .arg("-f")
+                .arg(selector)
+                .arg("--merge-output-format")
+                .arg("mp4")
+                .arg("-o")
+                .arg(&video_path)
+                .arg("--no-playlist")
+                .args(yt_dlp_output_args(cli.quiet));
+
+            if fetch_subs {
+                dl_cmd.arg("--write-sub");
+                if !cli.no_auto_sub && !cli.prefer_manual {
+                    dl_cmd.arg("--write-auto-sub");
+                }
+                dl_cmd
+                    .arg("--sub-lang")
+                    .arg(&cli.lang)
+                    .arg("--convert-subs")
+                    .arg("srt");
+            }
+
+            dl_cmd.arg(input);
+
+            let dl_status = dl_cmd.status().context("Failed to run yt-dlp")?;
+            if dl_status.success() {
+                if i > 0 {
+                    println!("Downloaded with format \"{}\"", selector);
+                }
+                downloaded = true;
+                break;
+            }
+        }
+
+        if !downloaded {
+            fail!(
+                Failure::Download,
+                "yt-dlp failed to download video (tried formats: {})",
+                YT_DLP_FORMATS.join(", ")
+            );
+        }
+
+        // Handle subtitles
+        let sub_path = if let Some(ref subs_input) = cli.subs {
+            Some(resolve_subs_input(subs_input, temp_path)?)
+        } else {
+            let found = find_subtitle_file(temp_path, &cli.lang);
+            let found = if found.is_none() && fetch_subs && cli.prefer_manual {
+                eprintln!("Warning: No manual subtitles found, falling back to auto-generated captions");
+                download_auto_subs(&yt_dlp, input, &cli.lang, &video_path, cli.quiet)?;
+                find_subtitle_file(temp_path, &cli.lang)
+            } else {
+                found
+            };
+
+            if fetch_subs
+                && let (Some(subs), Some(cache)) = (&found, &sub_cache)
+                && let Err(e) = store_cached_subs(subs, cache)
+            {
+                eprintln!("Warning: Could not cache subtitles: {:#}", e);
+            }
+            found
+        };
+
+        let media = probe_source(&config, &video_path, false);
+        (video_path, video_title, sub_path, media)
+    } else if is_hls_url(input) {
+        // HLS stream mode - ffmpeg reads the playlist directly, so only the clipped
+        // segments are fetched and live streams work without downloading
+        let video_path = PathBuf::from(input);
+        let video_title = get_filename_from_url(input);
+        println!("Stream: {}", video_title);
+
+        let sub_path = if let Some(ref subs_input) = cli.subs {
+            Some(resolve_subs_input(subs_input, temp_path)?)
+        } else {
+            None
+        };
+
+        let media = probe_source(&config, &video_path, false);
+        (video_path, video_title, sub_path, media)
+    } else if is_url(input) {
+        // Direct URL mode - download video, check embedded subs only
+        println!("Downloading video...");
+        let ext = Path::new(input)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("mp4");
+        let video_path = temp_path.join(format!("video.{}", ext));
+        let served_name = download_file(input, &video_path).failure(Failure::Download)?;
+
+        // Opaque download links (/download?id=123) name the file in the response instead
+        let video_title = match served_name {
+            Some(name) => Path::new(&name)
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or(&name)
+                .to_string(),
+            None => get_filename_from_url(input),
+        };
+        println!("Video: {}", video_title);
+        let media = probe_source(&config, &video_path, false);
+
+        // Handle subtitles - explicit subs or try embedded
+        let sub_path = if let Some(ref subs_input) = cli.subs {
+            Some(resolve_subs_input(subs_input, temp_path)?)
+        } else if !skip_subs {
+            if let Some(extracted_subs) = extract_embedded_subs(&media, &ffmpeg, &video_path, temp_path)? {
+                println!("Extracted embedded subtitles");
+                Some(extracted_subs)
+            } else if cli.fetch_subs {
+                fetch_opensubtitles(&config, &video_path, &cli.lang, temp_path)?
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        (video_path, video_title, sub_path, media)
+    } else {
+        // Local file mode - check embedded subs, then adjacent .srt
+        let video_path = PathBuf::from(input);
+        if !video_path.exists() {
+            fail!(Failure::Usage, "Input file does not exist: {}", input);
+        }
+
+        let video_title = get_filename_from_path(input);
+        println!("Video: {}", video_title);
+        let media = probe_source(&config, &video_path, cli.probe_cache);
+
+        // Handle subtitles - explicit, embedded, or adjacent file
+        let sub_path = if let Some(ref subs_input) = cli.subs {
+            Some(resolve_subs_input(subs_input, temp_path)?)
+        } else if !skip_subs {
+            // First try embedded subs
+            if let Some(extracted_subs) = extract_embedded_subs(&media, &ffmpeg, &video_path, temp_path)? {
+                println!("Extracted embedded subtitles");
+                Some(extracted_subs)
+            } else {
+                // Look for adjacent subtitle file with same name
+                let adjacent = find_adjacent_subtitle(&video_path);
+                if adjacent.is_none() && cli.fetch_subs {
+                    fetch_opensubtitles(&config, &video_path, &cli.lang, temp_path)?
+                } else {
+                    adjacent
+                }
+            }
+        } else {
+            None
+        };
+
+        (video_path, video_title, sub_path, media)
+    };
+
+    stats.lap("Fetch input and subtitles");
+
+    let video_title = cli.title.clone().unwrap_or(video_title);
+
+    let sub_path = if sub_path.is_none() && cli.transcribe && cli.text.is_none() {
+        println!("Transcribing audio...");
+        let whisper = transcribe::Whisper::find()?;
+        let model = cli.whisper_model.as_deref().or(config.whisper_model.as_deref());
+        let path = transcribe::transcribe(
+            &ffmpeg,
+            &whisper,
+            model,
+            &video_path,
+            transcription_range(&cli)?,
+            &cli.lang,
+            temp_path,
+        )?;
+        stats.lap("Transcription");
+        Some(path)
+    } else {
+        sub_path
+    };
+
+    // Decode non-UTF-8 subtitles so both dialogue search and ffmpeg see UTF-8
+    let sub_path = match sub_path {
+        Some(path) => Some(srt::ensure_utf8(&path, &cli.sub_encoding, temp_path)?),
+        None => None,
+    };
+
+    stats.lap("Subtitle decoding");
+
+    let needs_other_language =
+        cli.search_all_langs && !sub_path.as_deref().is_some_and(|path| subs_match_queries(path, &cli));
+    let sub_path = if needs_other_language {
+        match search_other_languages(&config, &media, input, &video_path, sub_path.as_deref(), &cli, temp_path)? {
+            Some((lang, path)) => {
+                println!("Found the dialogue in the \"{}\" subtitles", lang);
+                Some(path)
+            }
+            None => {
+                eprintln!("Warning: No other subtitle language has the dialogue either");
+                sub_path
+            }
+        }
+    } else {
+        sub_path
+    };
+
+    // Determine start/end times
+    // Extra dialogue segments when several --from quotes build a supercut
+    let mut supercut_segments = Vec::new();
+
+    let (start_secs, end_secs) = if !cli.from.is_empty() {
+        // Dialogue mode - search subtitles
+        let sub_file = sub_path.as_ref()
+            .context("Subtitles required for dialogue search but none found")
+            .failure(Failure::NotFound)?;
+
+        let entries = srt::merge_held_captions(srt::parse_subtitles(sub_file)?);
+
+        if !cli.to.is_empty() && cli.to.len() != cli.from.len() {
+            fail!(Failure::Usage, "Give every --from its own --to, or use no --to at all");
+        }
+
+        let mut segments = Vec::new();
+        for (i, from_text) in cli.from.iter().enumerate() {
+            let to_text = cli.to.get(i).map(String::as_str);
+            let (start, end, default_pad) =
+                find_dialogue_segment(&entries, from_text, to_text, cli.exact_diacritics, &config)
+                    .failure(Failure::NotFound)?;
+            segments.push(pad_dialogue_span(start, end, default_pad, &cli, &ffmpeg, &video_path, &media));
+        }
+
+        if cli.supercut_order == SupercutOrder::Appearance {
+            segments.sort_by(|a, b| a.0.total_cmp(&b.0));
+        }
+
+        let clip_bounds = (segments[0].0, segments[segments.len() - 1].1);
+        if segments.len() > 1 {
+            if cli.seamless || cli.interactive_trim || cli.stabilize {
+                fail!(Failure::Usage, "--seamless, --interactive-trim and --stabilize work on a single clip, not a supercut");
+            }
+            supercut_segments = segments;
+        }
+
+        clip_bounds
+    } else if let Some((first, last)) = cli.cue.map(|n| (n, n)).or(cli.cue_range) {
+        // Cue mode - subtitle entries by their number in the file
+        let sub_file = sub_path.as_ref()
+            .context("Subtitles required for --cue but none found")
+            .failure(Failure::NotFound)?;
+        let entries = srt::parse_subtitles(sub_file)?;
+        let find_cue = |n: u32| {
+            entries.iter().find(|e| e.index == n as usize).with_context(|| {
+                let highest = entries.iter().map(|e| e.index).max().unwrap_or(0);
+                format!("No subtitle cue {} (cues run up to {})", n, highest)
+            })
+        };
+        let first_cue = find_cue(first).failure(Failure::NotFound)?;
+        let last_cue = find_cue(last).failure(Failure::NotFound)?;
+        println!("Cue {}: \"{}\"", first_cue.index, first_cue.text);
+        if last != first {
+            println!("Cue {}: \"{}\"", last_cue.index, last_cue.text);
+        }
+
+        let default_pad = if first == last {
+            config.default_pad_single.unwrap_or(2.0)
+        } else {
+            config.default_pad_range.unwrap_or(0.5)
+        };
+        pad_dialogue_span(
+            first_cue.start,
+            last_cue.end.max(first_cue.end),
+            default_pad,
+            &cli,
+            &ffmpeg,
+            &video_path,
+            &media,
+        )
+    } else if let Some(ref chapter_query) = cli.chapter {
+        // Chapter mode - use the chapter's bounds
+        let chapters = load_chapters(&config, input, &video_path)?;
+        let chapter = chapters::find_chapter(&chapters, chapter_query).failure(Failure::NotFound)?;
+        println!(
+            "Found chapter \"{}\" at {:.1}s - {:.1}s",
+            chapter.title, chapter.start, chapter.end
+        );
+
+        (chapter.start, chapter.end)
+    } else if let (Some(file), Some(clip_query)) = (&cli.timestamps_file, &cli.clip) {
+        // Marker mode - like chapters, but from the user's own timestamps file
+        let markers = chapters::from_timestamps_file(file)?;
+        let marker = chapters::find_chapter(&markers, clip_query).failure(Failure::NotFound)?;
+        let end = if marker.end.is_finite() {
+            marker.end
+        } else {
+            get_video_duration(&config, &media, &video_path)?
+        };
+        println!("Found marker \"{}\" at {:.1}s - {:.1}s", marker.title, marker.start, end);
+
+        (marker.start, end)
+    } else if cli.frame_start.is_some() || cli.frame_end.is_some() {
+        // Frame mode - convert frame numbers to times using the source frame rate
+        let (fps, frame_count) = get_video_frame_info(&media)?;
+        let frame_start = cli.frame_start.unwrap_or(0);
+        let frame_end = cli.frame_end.unwrap_or(frame_count.saturating_sub(1));
+
+        if frame_end <= frame_start {
+            fail!(Failure::Usage, "End frame must be after start frame");
+        }
+        if frame_end >= frame_count {
+            fail!(
+                Failure::Usage,
+                "End frame {} is past the last frame ({} frames, numbered from 0)",
+                frame_end,
+                frame_count
+            );
+        }
+
+        println!("Frames {}-{} at {:.3} fps", frame_start, frame_end, fps);
+
+        // The end frame is inclusive
+        (frame_start as f64 / fps, (frame_end + 1) as f64 / fps)
+    } else if let Some(ref at) = cli.at {
+        // Window mode - a fixed-length clip centered on one timestamp
+        let center = parse_timestamp(at)?;
+        let around = cli.around.context("--at needs --around <SECONDS>")?;
+        let start = (center - around / 2.0).max(0.0);
+        (start, clamp_window_end(center + around / 2.0, &media))
+    } else {
+        // Timestamp mode - handle optional start/end
+        let start_secs = if let Some(ref start) = cli.start {
+            parse_timestamp(start)?
+        } else if let Some(gap) = cli.continue_after {
+            let last_end = history::last_clip_end(input)
+                .context("No previous clip of this input to continue from; give --start for the first one")?;
+            println!("Continuing from the last clip's end at {:.1}s", last_end);
+            last_end + gap
+        } else {
+            0.0
+        };
+
+        let end_secs = if let Some(ref end) = cli.end {
+            parse_timestamp(end)?
+        } else if let Some(ref duration) = cli.duration {
+            start_secs + parse_timestamp(duration)?
+        } else if is_hls_url(input) {
+            // Live playlists have no duration to fall back on
+            get_video_duration(&config, &media, &video_path).context(
+                "Could not determine stream duration (live streams need --end or --duration)",
+            )?
+        } else {
+            // Get video duration
+            get_video_duration(&config, &media, &video_path)?
+        };
+
+        if end_secs <= start_secs {
+            fail!(Failure::Usage, "End time must be after start time");
+        }
+
+        (start_secs, end_secs)
+    };
+
+    let (start_secs, end_secs) = if cli.interactive_trim {
+        trim::interactive_trim(&ffmpeg, &video_path, temp_path, start_secs, end_secs, cli.fps)?
+    } else {
+        (start_secs, end_secs)
+    };
+
+    let (start_secs, end_secs) = if cli.seamless {
+        find_seamless_loop(&ffmpeg, &video_path, start_secs, end_secs, cli.fps)?
+    } else {
         (start_secs, end_secs)
     };
 
-    let duration = end_secs - start_secs;
-    println!(
-        "Clipping {:.1}s from {:.1}s to {:.1}s",
-        duration, start_secs, end_secs
+    stats.lap("Clip selection");
+
+    let duration = end_secs - start_secs;
+    if supercut_segments.is_empty() {
+        println!(
+            "Clipping {:.1}s from {:.1}s to {:.1}s",
+            duration, start_secs, end_secs
+        );
+    }
+
+    if cli.export_subs.is_some() || cli.export_edl.is_some() {
+        let segments = if supercut_segments.is_empty() {
+            vec![(start_secs, end_secs)]
+        } else {
+            supercut_segments
+        };
+
+        if let Some(ref export) = cli.export_subs {
+            let sub_file = sub_path
+                .as_ref()
+                .context("No subtitles found to export")
+                .failure(Failure::NotFound)?;
+            let mut entries = srt::trim_to_segments(&srt::parse_subtitles(sub_file)?, &segments);
+            rewrite_entries(&mut entries, &cli);
+            if subtitle_extension(&export.to_string_lossy()).as_deref() == Some("vtt") {
+                srt::write_vtt(&entries, export)?;
+            } else {
+                srt::write_srt(&entries, export)?;
+            }
+            println!("Exported {} subtitle(s): {}", entries.len(), export.display());
+        }
+
+        if let Some(ref export) = cli.export_edl {
+            let is_edl = export
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("edl"));
+            if is_edl {
+                let (fps, _) = get_video_frame_info(&media)
+                    .context("An EDL needs the source frame rate for its timecodes")?;
+                // NLEs relink events by source file name; downloads only have a title
+                let clip_name = if is_url(input) {
+                    video_title.clone()
+                } else {
+                    video_path.file_name().map_or(video_title.clone(), |name| name.to_string_lossy().into_owned())
+                };
+                edl::write_edl(export, &video_title, &clip_name, &segments, fps)?;
+            } else {
+                edl::write_ffmetadata(export, &video_title, &segments)?;
+            }
+            println!("Exported {} segment(s): {}", segments.len(), export.display());
+        }
+
+        return Ok(());
+    }
+
+    let scenes = if cli.split_scenes {
+        if !supercut_segments.is_empty() {
+            fail!(Failure::Usage, "--split-scenes splits a single clip, not a supercut");
+        }
+        let cuts = detect_scene_cuts(&ffmpeg, &video_path, start_secs, end_secs, cli.scene_threshold)?;
+        let scenes = split_at_cuts(start_secs, end_secs, &cuts);
+        println!("Found {} scene(s)", scenes.len());
+        stats.lap("Scene detection");
+        scenes
+    } else {
+        vec![(start_secs, end_secs)]
+    };
+    let scene_count = scenes.len();
+
+    let has_subs = !skip_subs && sub_path.is_some();
+    if !skip_subs && !has_subs && cli.text.is_none() {
+        eprintln!("Warning: No subtitles found, proceeding without them");
+    }
+
+    let sub_path = match sub_path {
+        // Rewriting also decodes HTML entities, which the subtitles filter would draw as-is
+        Some(path)
+            if cli.sub_max_chars.is_some() || cli.sub_case != SubCase::None || srt::has_html_entities(&path) =>
+        {
+            Some(rewrite_subtitles(&path, &cli, temp_path)?)
+        }
+        other => other,
+    };
+
+    if let Some(ref keep) = cli.keep_subs {
+        match &sub_path {
+            Some(path) => {
+                fs::copy(path, keep)
+                    .with_context(|| format!("Failed to save subtitles to {}", keep.display()))?;
+                println!("Saved subtitles: {}", keep.display());
+            }
+            None => eprintln!("Warning: No subtitle file to save for --keep-subs"),
+        }
+    }
+
+    let ctx = ClipContext {
+        cli: &cli,
+        config: &config,
+        ffmpeg: &ffmpeg,
+        video_path: &video_path,
+        video_title: &video_title,
+        media: &media,
+        temp_path,
+        sub_path: sub_path.as_deref(),
+        supercut_segments: &supercut_segments,
+        scene_count,
+    };
+    for (scene, range) in scenes.into_iter().enumerate() {
+        let clip = prepare_clip(&ctx, &mut stats, scene, range)?;
+        // Everything from here is per output format; the analysis above is shared
+        for &format in &cli.formats {
+            encode_clip(&ctx, &mut stats, &clip, format)?;
+        }
+    }
+
+    if let Err(e) = history::record_clip_end(input, end_secs) {
+        eprintln!("Warning: Could not remember where this clip ended: {:#}", e);
+    }
+
+    if cli.stats {
+        stats.print();
+    }
+
+    Ok(())
+}
+
+/// What every clip of a run shares: the source, its subtitles and the tools
+struct ClipContext<'a> {
+    cli: &'a Cli,
+    config: &'a config::Config,
+    ffmpeg: &'a Path,
+    video_path: &'a Path,
+    video_title: &'a str,
+    media: &'a MediaInfo,
+    temp_path: &'a Path,
+    /// Subtitles to show, already rewritten for --sub-case/--sub-max-chars
+    sub_path: Option<&'a Path>,
+    supercut_segments: &'a [(f64, f64)],
+    /// Clips --split-scenes makes of the selection (1 without it)
+    scene_count: usize,
+}
+
+/// One clip (a scene, with --split-scenes), analysed once and then encoded per --format
+struct Clip {
+    /// 0-based scene number
+    scene: usize,
+    start_secs: f64,
+    end_secs: f64,
+    /// Range the encoders read, from 0 when the graph trims and retimes the clip itself
+    encode_start: f64,
+    encode_duration: f64,
+    poster_time: Option<f64>,
+    /// Subtitles are set to burn; each format decides whether they become a track instead
+    assets: FilterAssets,
+}
+
+/// Work out everything about the clip that doesn't depend on the output format: speed and
+/// audio filters, rotation, cropping, cards, edge padding and the analysis passes
+fn prepare_clip(
+    ctx: &ClipContext,
+    stats: &mut Stats,
+    scene: usize,
+    (start_secs, end_secs): (f64, f64),
+) -> Result<Clip> {
+    let ClipContext { cli, config, ffmpeg, video_path, media, temp_path, sub_path, supercut_segments, scene_count, .. } = *ctx;
+
+    let duration = end_secs - start_secs;
+    if scene_count > 1 {
+        println!(
+            "\nScene {}/{}: {:.1}s from {:.1}s to {:.1}s",
+            scene + 1,
+            scene_count,
+            duration,
+            start_secs,
+            end_secs
+        );
+    }
+
+    // A speed ramp retimes the clip inside the filter graph, after trimming it there.
+    // A constant --speed is the same thing with a single keyframe at the start.
+    let ramp = cli
+        .speed_ramp
+        .clone()
+        .or_else(|| cli.speed.map(|speed| SpeedRamp { keyframes: vec![(0.0, speed)] }));
+    let speed_ramp_filter = match ramp {
+        Some(ref ramp) => {
+            if !supercut_segments.is_empty() {
+                fail!(Failure::Usage, "--speed and --speed-ramp work on a single clip, not a supercut");
+            }
+            Some(build_speed_ramp_filter(ramp, start_secs, end_secs)?)
+        }
+        None => None,
+    };
+
+    if (cli.audio || cli.audio_only) && !supercut_segments.is_empty() {
+        fail!(Failure::Usage, "--audio and --audio-only work on a single clip, not a supercut");
+    }
+
+    // The audio is trimmed and retimed by its own filter to match the video graph
+    let audio_track = if cli.audio || cli.audio_only {
+        select_audio_track(media, cli)?
+    } else {
+        None
+    };
+
+    let audio_filter = match cli.speed {
+        Some(speed) if cli.audio || cli.audio_only => Some(build_audio_speed_filter(
+            config,
+            media,
+            audio_track.unwrap_or(0),
+            speed,
+            cli.preserve_pitch,
+            start_secs,
+            end_secs,
+        )?),
+        _ => None,
+    };
+
+    let audio_filter = if cli.normalize_audio {
+        let loudnorm = measure_loudness(ffmpeg, video_path, audio_track.unwrap_or(0), start_secs, duration)?;
+        stats.lap("Loudness analysis");
+        match (audio_filter, loudnorm) {
+            (Some(filter), Some(loudnorm)) => Some(format!("{},{}", filter, loudnorm)),
+            (filter, loudnorm) => filter.or(loudnorm),
+        }
+    } else {
+        audio_filter
+    };
+
+    // Speed ramps and supercuts are trimmed inside the filter graph, so the encoders
+    // take the whole resulting stream from 0
+    let (encode_start, encode_duration) = if let Some((_, ramped_duration)) = speed_ramp_filter {
+        println!("Speed change: {:.1}s of source plays as {:.1}s", duration, ramped_duration);
+        (0.0, ramped_duration)
+    } else if supercut_segments.is_empty() {
+        (start_secs, duration)
+    } else {
+        let total: f64 = supercut_segments.iter().map(|(s, e)| e - s).sum();
+        println!("Supercut of {} segments, {:.1}s total", supercut_segments.len(), total);
+        (0.0, total)
+    };
+
+    for format in &cli.formats {
+        let max_duration = cli.max_duration.unwrap_or(match format {
+            OutputFormat::Gif | OutputFormat::Webp => DEFAULT_MAX_ANIMATION_DURATION,
+            OutputFormat::Mp4 | OutputFormat::Webm => 0.0,
+        });
+        if !cli.audio_only && max_duration > 0.0 && encode_duration > max_duration {
+            let video_hint = match format {
+                OutputFormat::Gif | OutputFormat::Webp => "use -f mp4, ",
+                OutputFormat::Mp4 | OutputFormat::Webm => "",
+            };
+            fail!(
+                Failure::Usage,
+                "The clip is {:.1}s, over the {:.0}s limit for {:?} output. Check the timestamps, {}or raise --max-duration (0 for no limit)",
+                encode_duration,
+                max_duration,
+                format,
+                video_hint
+            );
+        }
+    }
+
+    let poster_time = match cli.poster {
+        Some(ref poster) => {
+            let at = parse_timestamp(poster).failure(Failure::Usage)?;
+            if at >= encode_duration {
+                fail!(
+                    Failure::Usage,
+                    "--poster {} is past the end of the {:.1}s clip; it counts from the clip's start",
+                    poster,
+                    encode_duration
+                );
+            }
+            Some(at)
+        }
+        None => None,
+    };
+
+    let hdr = !cli.audio_only && is_hdr(media);
+    if hdr && !cli.tonemap {
+        println!("Note: HDR source; add --tonemap if the clip looks washed out");
+    } else if cli.tonemap && !hdr {
+        println!("Note: source isn't HDR, skipping --tonemap");
+    }
+
+    // Phone videos store frames sideways plus a rotation flag. Whether ffmpeg honours the
+    // flag depends on version and options, so turn autorotate off and rotate explicitly.
+    let rotation = match cli.rotate {
+        Some(degrees) => Some(degrees),
+        None if cli.audio_only => None,
+        None => match video_rotation(media) {
+            0 => None,
+            degrees => {
+                println!("Source is rotated {}°, turning it upright (override with --rotate)", degrees);
+                Some(degrees)
+            }
+        },
+    };
+
+    let crop = match cli.crop {
+        Some(crop) => Some(crop),
+        None if cli.trim_black && !cli.audio_only => {
+            let source = oriented_dimensions(get_video_dimensions(media)?, rotation);
+            let detected = detect_black_bars(ffmpeg, video_path, source, rotation, start_secs, end_secs)?;
+            match detected {
+                Some(c) => println!("Detected black bars, cropping to {}:{}:{}:{}", c.width, c.height, c.x, c.y),
+                None => println!("No black bars detected"),
+            }
+            stats.lap("Black bar detection");
+            detected
+        }
+        None => None,
+    };
+
+    // Cards are concatenated around the clip inside the graph, which then starts at 0
+    let card_seconds: f64 = cli.intro.iter().chain(&cli.outro).map(|c| c.seconds).sum();
+    let (cards, encode_start, encode_duration) = if card_seconds > 0.0 {
+        let (width, height) = match crop {
+            Some(crop) => (crop.width, crop.height),
+            None => oriented_dimensions(get_video_dimensions(media)?, rotation),
+        };
+        let cards = CardLayout {
+            width,
+            height,
+            clip_start: encode_start,
+            clip_end: encode_start + encode_duration,
+        };
+        (Some(cards), 0.0, encode_duration + card_seconds)
+    } else {
+        (None, encode_start, encode_duration)
+    };
+
+    // Edge frames are added to the graph's output; like cards, that retimes it to start at 0
+    let edge_pad = cli.pad_black.or(cli.pad_hold).filter(|&seconds| seconds > 0.0).map(|seconds| {
+        let retimed = !supercut_segments.is_empty() || speed_ramp_filter.is_some() || cards.is_some();
+        EdgePad {
+            seconds,
+            hold: cli.pad_hold.is_some(),
+            trim: (!retimed).then_some((encode_start, encode_start + encode_duration)),
+        }
+    });
+    let (encode_start, encode_duration) = match edge_pad {
+        Some(ref pad) => (0.0, encode_duration + 2.0 * pad.seconds),
+        None => (encode_start, encode_duration),
+    };
+
+    let stabilize_transforms = if cli.stabilize {
+        Some(detect_stabilization(ffmpeg, video_path, temp_path, rotation, start_secs, duration)?)
+    } else {
+        None
+    };
+
+    if cli.stabilize {
+        stats.lap("Stabilization analysis");
+    }
+
+    let subs_on_top = if cli.smart_sub_position && (sub_path.is_some() || cli.text.is_some()) {
+        let busy = bottom_strip_is_busy(ffmpeg, video_path, start_secs, start_secs + duration)?;
+        if busy {
+            println!("Bottom of the frame looks busy, moving subtitles to the top");
+        }
+        stats.lap("Subtitle position analysis");
+        busy
+    } else {
+        false
+    };
+
+    let source_start = supercut_segments.iter().map(|(s, _)| *s).fold(start_secs, f64::min);
+
+    let assets = FilterAssets {
+        source_start,
+        rotation,
+        tonemap: hdr && cli.tonemap,
+        crop,
+        sub_path: sub_path.map(Path::to_path_buf),
+        soft_subs: None,
+        subs_on_top,
+        stabilize_transforms,
+        supercut_segments: supercut_segments.to_vec(),
+        speed_ramp_filter: speed_ramp_filter.map(|(filter, _)| filter),
+        audio_filter,
+        audio_track,
+        cards,
+        edge_pad,
+    };
+
+    Ok(Clip {
+        scene,
+        start_secs,
+        end_secs,
+        encode_start,
+        encode_duration,
+        poster_time,
+        assets,
+    })
+}
+
+/// Encode a prepared clip in one output format
+fn encode_clip(ctx: &ClipContext, stats: &mut Stats, clip: &Clip, format: OutputFormat) -> Result<()> {
+    let ClipContext { cli, config, ffmpeg, video_path, video_title, media, temp_path, sub_path, scene_count, .. } = *ctx;
+    let Clip { scene, start_secs, end_secs, encode_start, encode_duration, poster_time, .. } = *clip;
+
+    // Determine output path
+    let ext = match format {
+        _ if cli.audio_only => "mp3",
+        OutputFormat::Gif => "gif",
+        OutputFormat::Webm => "webm",
+        OutputFormat::Mp4 => "mp4",
+        OutputFormat::Webp => "webp",
+    };
+    // Scenes are numbered through {index}, or a suffix when the name doesn't use it.
+    // With several formats, --output's extension follows each one.
+    let output_path = match &cli.output {
+        Some(p) => {
+            let mut path = p.clone();
+            if scene_count > 1 {
+                let stem = p.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+                let name = match p.extension() {
+                    Some(ext) => format!("{}_{:02}.{}", stem, scene + 1, ext.to_string_lossy()),
+                    None => format!("{}_{:02}", stem, scene + 1),
+                };
+                path.set_file_name(name);
+            }
+            if cli.formats.len() > 1 {
+                path.set_extension(ext);
+            }
+            path
+        }
+        None => {
+            let template = cli
+                .output_template
+                .as_deref()
+                .or(config.output_template.as_deref())
+                .unwrap_or(DEFAULT_OUTPUT_TEMPLATE);
+            let vars = [
+                ("title", sanitize_filename(video_title)),
+                ("start", format_timestamp(start_secs)),
+                ("end", format_timestamp(end_secs)),
+                ("duration", format_timestamp(encode_duration)),
+                ("width", cli.width.to_string()),
+                ("fps", cli.fps.to_string()),
+                ("format", ext.to_string()),
+                ("index", if scene_count > 1 { scene + 1 } else { cli.batch_index }.to_string()),
+            ];
+            let name = render_output_template(template, &vars)?;
+            if scene_count > 1 && !template.contains("{index}") {
+                PathBuf::from(format!("{}_{:02}.{}", name, scene + 1, ext))
+            } else {
+                PathBuf::from(format!("{}.{}", name, ext))
+            }
+        }
+    };
+
+    // Video formats can carry subtitles as a selectable track instead of burning them in.
+    // The track keeps source timing, so clips retimed inside the graph still burn.
+    let soft_subs_blocker = if matches!(format, OutputFormat::Gif | OutputFormat::Webp) {
+        Some("GIF and WebP have no subtitle tracks")
+    } else if !clip.assets.supercut_segments.is_empty()
+        || clip.assets.speed_ramp_filter.is_some()
+        || clip.assets.cards.is_some()
+        || clip.assets.edge_pad.is_some()
+    {
+        Some("supercuts, speed changes, cards and edge padding retime the clip")
+    } else {
+        None
+    };
+    let (sub_path, soft_subs) = match sub_path.map(Path::to_path_buf) {
+        Some(path) if cli.soft_subs => match soft_subs_blocker {
+            None => (None, Some(path)),
+            Some(reason) => {
+                eprintln!("Warning: Burning subtitles instead of adding a track: {}", reason);
+                (Some(path), None)
+            }
+        },
+        other => (other, None),
+    };
+
+    let assets = FilterAssets { sub_path, soft_subs, ..clip.assets.clone() };
+
+    let source_size = match assets.crop {
+        Some(crop) => Ok((crop.width, crop.height)),
+        None => get_video_dimensions(media).map(|size| oriented_dimensions(size, assets.rotation)),
+    };
+    if !cli.audio_only
+        && let Ok(source) = source_size
+    {
+        let (width, height) = output_dimensions(cli, source);
+        println!("Output size: {}x{}", width, height);
+        if source.1 > source.0 && cli.height.is_none() && cli.blur_pad.is_none() {
+            println!(
+                "Note: portrait source; use --height to size by height instead (e.g. --height {})",
+                cli.width
+            );
+        }
+    }
+
+    // Build and run ffmpeg
+    println!("Generating {}...", output_path.display());
+
+    let stream_copy = cli.copy_codecs
+        && match stream_copy_blocker(media, &assets, cli, format) {
+            None => true,
+            Some(reason) => {
+                println!("Can't stream copy ({}), re-encoding instead", reason);
+                false
+            }
+        };
+
+    let encoded = if cli.audio_only {
+        extract_audio(ffmpeg, video_path, &output_path, &assets, cli, encode_start, encode_duration)
+    } else if stream_copy {
+        // A copy has to begin on a keyframe or the first frames decode as garbage, so start
+        // at the one before the requested start and keep the same end
+        let (copy_start, copy_duration) = match preceding_keyframe(config, video_path, encode_start) {
+            Some(keyframe) if keyframe < encode_start - 0.001 => {
+                println!(
+                    "Stream copy starts at the keyframe at {:.3}s, {:.3}s before the requested start",
+                    keyframe,
+                    encode_start - keyframe
+                );
+                (keyframe, encode_duration + (encode_start - keyframe))
+            }
+            Some(_) => (encode_start, encode_duration),
+            None => {
+                eprintln!("Warning: Couldn't find the source keyframes; the clip may start with damaged frames");
+                (encode_start, encode_duration)
+            }
+        };
+        copy_streams(ffmpeg, video_path, &output_path, cli, assets.audio_track, copy_start, copy_duration)
+    } else {
+        match format {
+            OutputFormat::Gif => encode_gif(ffmpeg, video_path, &output_path, &assets, cli, encode_start, encode_duration),
+            OutputFormat::Webm => encode_webm(ffmpeg, video_path, &output_path, &assets, cli, encode_start, encode_duration),
+            OutputFormat::Mp4 => encode_mp4(ffmpeg, video_path, &output_path, &assets, cli, encode_start, encode_duration),
+            OutputFormat::Webp => encode_webp(ffmpeg, video_path, &output_path, &assets, cli, encode_start, encode_duration),
+        }
+    };
+    encoded.failure(Failure::Encode)?;
+
+    // Palette generation runs inside the same ffmpeg pass, so it's counted here
+    stats.lap("Encode");
+
+    if let Some(at) = poster_time
+        && format == OutputFormat::Mp4
+    {
+        attach_poster(ffmpeg, &output_path, temp_path, at).failure(Failure::Encode)?;
+        stats.lap("Poster");
+    }
+
+    println!("Created: {}", output_path.display());
+
+    if !cli.quiet {
+        print_output_report(config, &output_path, format);
+        stats.lap("Output report");
+    }
+
+    Ok(())
+}
+
+/// Wall-clock time spent in each phase of a run, for --stats
+struct Stats {
+    started: Instant,
+    last: Instant,
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl Stats {
+    fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            started: now,
+            last: now,
+            phases: Vec::new(),
+        }
+    }
+
+    /// Record the time since the previous lap under `phase`
+    fn lap(&mut self, phase: &'static str) {
+        let now = Instant::now();
+        self.phases.push((phase, now - self.last));
+        self.last = now;
+    }
+
+    fn print(&self) {
+        let total = self.last - self.started;
+        println!("\nTiming:");
+        for (phase, elapsed) in &self.phases {
+            let share = if total.is_zero() {
+                0.0
+            } else {
+                elapsed.as_secs_f64() / total.as_secs_f64() * 100.0
+            };
+            println!("  {:<26} {:>8.2}s  {:>5.1}%", phase, elapsed.as_secs_f64(), share);
+        }
+        println!("  {:<26} {:>8.2}s", "Total", total.as_secs_f64());
+    }
+}
+
+/// Print dimensions, frame count, file size and (for GIF) palette size of the encoded output
+fn print_output_report(config: &config::Config, output_path: &Path, format: OutputFormat) {
+    if let Ok(ffprobe) = config.ffprobe_path()
+        && let Ok(output) = Command::new(&ffprobe)
+            .arg("-v")
+            .arg("error")
+            .arg("-select_streams")
+            .arg("v:0")
+            .arg("-count_frames")
+            .arg("-show_entries")
+            .arg("stream=width,height,nb_read_frames")
+            .arg("-of")
+            .arg("csv=p=0")
+            .arg(output_path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .output()
+        && output.status.success()
+    {
+        let info = String::from_utf8_lossy(&output.stdout);
+        let fields: Vec<&str> = info.trim().split(',').collect();
+        if let [width, height, frames] = fields[..] {
+            println!("  Dimensions: {}x{}", width, height);
+            println!("  Frames: {}", frames);
+        }
+    }
+
+    if let Ok(metadata) = fs::metadata(output_path) {
+        println!("  Size: {}", format_size(metadata.len()));
+    }
+
+    if format == OutputFormat::Gif
+        && let Some(colors) = gif_palette_size(output_path)
+    {
+        println!("  Palette: {} colors", colors);
+    }
+}
+
+/// Read the global color table size from a GIF header
+fn gif_palette_size(path: &Path) -> Option<u32> {
+    let bytes = fs::read(path).ok()?;
+    if bytes.len() < 13 || !bytes.starts_with(b"GIF") {
+        return None;
+    }
+
+    // Logical screen descriptor packed field: high bit = table present, low 3 bits = size
+    let packed = bytes[10];
+    if packed & 0x80 == 0 {
+        return None;
+    }
+    Some(2u32 << (packed & 0x07))
+}
+
+fn format_size(bytes: u64) -> String {
+    const KB: f64 = 1024.0;
+    const MB: f64 = KB * 1024.0;
+
+    let bytes_f = bytes as f64;
+    if bytes_f >= MB {
+        format!("{:.1} MB", bytes_f / MB)
+    } else if bytes_f >= KB {
+        format!("{:.1} KB", bytes_f / KB)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+fn get_video_title(yt_dlp: &Path, url: &str) -> Result<String> {
+    let output = Command::new(yt_dlp)
+        .arg("--get-title")
+        .arg("--no-playlist")
+        .arg(url)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .context("Failed to get video title")?;
+
+    if !output.status.success() {
+        bail!("Failed to fetch video title");
+    }
+
+    let title = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok(title)
+}
+
+/// How far (seconds) --snap-silence may move a clip boundary
+const SILENCE_SNAP_WINDOW: f64 = 1.0;
+
+/// Nudge padded dialogue boundaries to the nearest silence within the snap window, never
+/// cutting into the dialogue itself. Falls back to the padded times if analysis fails.
+fn snap_to_silence(
+    ffmpeg: &Path,
+    video_path: &Path,
+    dialogue_start: f64,
+    dialogue_end: f64,
+    start: f64,
+    end: f64,
+) -> (f64, f64) {
+    let window_start = (start - SILENCE_SNAP_WINDOW).max(0.0);
+    let window_end = end + SILENCE_SNAP_WINDOW;
+
+    let silences = match detect_silences(ffmpeg, video_path, window_start, window_end) {
+        Ok(silences) => silences,
+        Err(e) => {
+            eprintln!("Warning: silence detection failed, keeping padded boundaries: {}", e);
+            return (start, end);
+        }
+    };
+
+    let nearest = |target: f64, allowed: &dyn Fn(f64) -> bool| {
+        silences
+            .iter()
+            .map(|(s, e)| target.clamp(*s, *e))
+            .filter(|t| allowed(*t) && (t - target).abs() <= SILENCE_SNAP_WINDOW)
+            .min_by(|a, b| (a - target).abs().total_cmp(&(b - target).abs()))
+    };
+
+    let snapped_start = nearest(start, &|t| t <= dialogue_start).unwrap_or(start);
+    let snapped_end = nearest(end, &|t| t >= dialogue_end).unwrap_or(end);
+
+    if snapped_start != start || snapped_end != end {
+        println!(
+            "Snapped to silence: {:.2}s - {:.2}s (was {:.2}s - {:.2}s)",
+            snapped_start, snapped_end, start, end
+        );
+    }
+
+    (snapped_start, snapped_end)
+}
+
+/// Run ffmpeg's silencedetect over part of the source, returning silent intervals in source time
+fn detect_silences(ffmpeg: &Path, video_path: &Path, from: f64, to: f64) -> Result<Vec<(f64, f64)>> {
+    let output = Command::new(ffmpeg)
+        .arg("-ss")
+        .arg(format!("{}", from))
+        .arg("-t")
+        .arg(format!("{}", to - from))
+        .arg("-i")
+        .arg(video_path)
+        .arg("-vn")
+        .arg("-af")
+        .arg("silencedetect=noise=-30dB:d=0.15")
+        .arg("-f")
+        .arg("null")
+        .arg("-")
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .context("Failed to run ffmpeg silence detection")?;
+
+    if !output.status.success() {
+        bail!("ffmpeg silencedetect failed (does the source have audio?)");
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let start_re = Regex::new(r"silence_start: (-?\d+(?:\.\d+)?)").unwrap();
+    let end_re = Regex::new(r"silence_end: (-?\d+(?:\.\d+)?)").unwrap();
+
+    // Input seeking resets timestamps, so offsets are relative to `from`
+    let mut silences = Vec::new();
+    let mut open_start: Option<f64> = None;
+    for line in stderr.lines() {
+        if let Some(caps) = start_re.captures(line) {
+            open_start = caps[1].parse::<f64>().ok();
+        } else if let Some(caps) = end_re.captures(line)
+            && let Ok(silence_end) = caps[1].parse::<f64>()
+        {
+            let silence_start = open_start.take().unwrap_or(0.0);
+            silences.push((from + silence_start.max(0.0), from + silence_end));
+        }
+    }
+
+    // Silence running to the end of the analysed range has no silence_end line
+    if let Some(silence_start) = open_start {
+        silences.push((from + silence_start.max(0.0), to));
+    }
+
+    Ok(silences)
+}
+
+/// Find one dialogue quote (or --from/--to range) and return its (start, end) along with the
+/// default padding for that kind of match
+fn find_dialogue_segment(
+    entries: &[srt::SubtitleEntry],
+    from_text: &str,
+    to_text: Option<&str>,
+    exact_diacritics: bool,
+    config: &config::Config,
+) -> Result<(f64, f64, f64)> {
+    let from_match = srt::find_dialogue(entries, from_text, exact_diacritics)
+        .with_context(|| format!("Could not find starting dialogue: \"{}\"", from_text))?;
+    warn_low_confidence(&from_match, from_text);
+    let from_entry = from_match.entry;
+
+    if let Some(to_text) = to_text {
+        // Range mode: from dialogue to dialogue
+        let to_match = srt::find_dialogue(entries, to_text, exact_diacritics)
+            .with_context(|| format!("Could not find ending dialogue: \"{}\"", to_text))?;
+        warn_low_confidence(&to_match, to_text);
+        let to_entry = to_match.entry;
+
+        if to_entry.end < from_entry.start {
+            bail!("Ending dialogue appears before starting dialogue");
+        }
+
+        Ok((from_entry.start, to_entry.end, config.default_pad_range.unwrap_or(0.5)))
+    } else {
+        // Single quote mode: just the one subtitle entry
+        Ok((from_entry.start, from_entry.end, config.default_pad_single.unwrap_or(2.0)))
+    }
+}
+
+/// Whether every --from/--to quote is found in a subtitle file with a confident match
+fn subs_match_queries(path: &Path, cli: &Cli) -> bool {
+    let Ok(entries) = srt::parse_subtitles(path).map(srt::merge_held_captions) else {
+        return false;
+    };
+    cli.from.iter().chain(&cli.to).all(|query| {
+        srt::find_dialogue(&entries, query, cli.exact_diacritics).is_ok_and(|m| !m.is_low_confidence())
+    })
+}
+
+/// --search-all-langs: fetch every other subtitle language the input has and return the
+/// first (language, file) where all the quotes are found
+fn search_other_languages(
+    config: &config::Config,
+    media: &MediaInfo,
+    input: &str,
+    video_path: &Path,
+    current: Option<&Path>,
+    cli: &Cli,
+    temp_path: &Path,
+) -> Result<Option<(String, PathBuf)>> {
+    println!("Dialogue not found; searching the other subtitle languages...");
+    let alt_dir = temp_path.join("all-langs");
+    fs::create_dir_all(&alt_dir)?;
+
+    let mut candidates: Vec<(String, PathBuf)> = Vec::new();
+    if is_url(input) && is_youtube_url(input) {
+        // Every uploaded track; auto captions would bring a machine translation per language
+        let status = Command::new(config.yt_dlp_path()?)
+            .arg("--skip-download")
+            .arg("--write-sub")
+            .arg("--sub-langs")
+            .arg("all,-live_chat")
+            .arg("--convert-subs")
+            .arg("srt")
+            .arg("-o")
+            .arg(alt_dir.join("video"))
+            .arg("--no-playlist")
+            .args(yt_dlp_output_args(true))
+            .arg(input)
+            .status()
+            .context("Failed to run yt-dlp")?;
+        if !status.success() {
+            eprintln!("Warning: yt-dlp couldn't list the other subtitle languages");
+        }
+        let mut files: Vec<PathBuf> = fs::read_dir(&alt_dir)?.filter_map(|e| e.ok().map(|e| e.path())).collect();
+        files.sort();
+        for path in files {
+            // video.LANG.srt
+            let name = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+            let lang = name.strip_prefix("video.").unwrap_or(&name).to_string();
+            candidates.push((lang, path));
+        }
+    } else {
+        let ffmpeg = config.ffmpeg_path()?;
+        for (i, (index, codec, lang)) in subtitle_streams(media).into_iter().enumerate() {
+            if BITMAP_SUB_CODECS.contains(&codec.as_str()) {
+                continue;
+            }
+            let ext = if codec == "ass" || codec == "ssa" { "ass" } else { "srt" };
+            let dest = alt_dir.join(format!("stream{}.{}", index, ext));
+            let extracted = Command::new(&ffmpeg)
+                .arg("-y")
+                .arg("-v")
+                .arg("error")
+                .arg("-i")
+                .arg(video_path)
+                .arg("-map")
+                .arg(format!("0:{}", index))
+                .arg(&dest)
+                .status()
+                .is_ok_and(|status| status.success());
+            if extracted {
+                let label = if lang.is_empty() { format!("embedded track {}", i + 1) } else { lang };
+                candidates.push((label, dest));
+            }
+        }
+        candidates.extend(adjacent_language_subtitles(video_path));
+    }
+
+    for (lang, path) in candidates {
+        if current.is_some_and(|current| current == path) {
+            continue;
+        }
+        let Ok(path) = srt::ensure_utf8(&path, &cli.sub_encoding, temp_path) else {
+            continue;
+        };
+        if subs_match_queries(&path, cli) {
+            return Ok(Some((lang, path)));
+        }
+    }
+    Ok(None)
+}
+
+/// (stream index, codec, language tag) of each subtitle stream in a file
+fn subtitle_streams(media: &MediaInfo) -> Vec<(usize, String, String)> {
+    media
+        .streams_of("subtitle")
+        .map(|s| (s.index, s.codec.clone(), s.language.clone()))
+        .collect()
+}
+
+/// Subtitle files next to a video named like `movie.fr.srt`, with their language part
+fn adjacent_language_subtitles(video_path: &Path) -> Vec<(String, PathBuf)> {
+    let (Some(stem), Some(parent)) = (video_path.file_stem(), video_path.parent()) else {
+        return Vec::new();
+    };
+    let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+    let prefix = format!("{}.", stem.to_string_lossy());
+
+    let Ok(entries) = fs::read_dir(parent) else {
+        return Vec::new();
+    };
+    let mut found: Vec<(String, PathBuf)> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter_map(|path| {
+            let name = path.file_name()?.to_string_lossy().into_owned();
+            let (rest, ext) = name.strip_prefix(&prefix)?.rsplit_once('.')?;
+            let known = ["srt", "ass", "ssa", "vtt", "lrc"].contains(&ext.to_lowercase().as_str());
+            (known && !rest.is_empty()).then(|| (rest.to_string(), path.clone()))
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+/// Source times (between `from` and `to`) where ffmpeg's scene score passes `threshold`
+fn detect_scene_cuts(ffmpeg: &Path, video_path: &Path, from: f64, to: f64, threshold: f64) -> Result<Vec<f64>> {
+    let output = Command::new(ffmpeg)
+        .arg("-ss")
+        .arg(format!("{}", from))
+        .arg("-t")
+        .arg(format!("{}", to - from))
+        .arg("-i")
+        .arg(video_path)
+        .arg("-an")
+        .arg("-vf")
+        .arg(format!("select='gt(scene,{})',showinfo", threshold))
+        .arg("-f")
+        .arg("null")
+        .arg("-")
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .context("Failed to run ffmpeg scene detection")?;
+
+    if !output.status.success() {
+        bail!("ffmpeg scene detection failed");
+    }
+
+    // Input seeking resets timestamps, so frame times are relative to `from`
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let pts_re = Regex::new(r"Parsed_showinfo.*\bpts_time:\s*(\d+(?:\.\d+)?)").unwrap();
+    Ok(stderr
+        .lines()
+        .filter_map(|line| pts_re.captures(line)?[1].parse::<f64>().ok())
+        .map(|t| from + t)
+        .collect())
+}
+
+/// Scenes this short are merged into their neighbour rather than becoming their own clip
+const MIN_SCENE_SECONDS: f64 = 0.5;
+
+/// Split `start..end` at the given cut times into consecutive (start, end) scenes
+fn split_at_cuts(start: f64, end: f64, cuts: &[f64]) -> Vec<(f64, f64)> {
+    let mut scenes = Vec::new();
+    let mut scene_start = start;
+    for &cut in cuts {
+        if cut - scene_start >= MIN_SCENE_SECONDS && end - cut >= MIN_SCENE_SECONDS {
+            scenes.push((scene_start, cut));
+            scene_start = cut;
+        }
+    }
+    scenes.push((scene_start, end));
+    scenes
+}
+
+/// Pad a dialogue span by --pad/--pad-before/--pad-after (or `default_pad`), or center an
+/// --around window on it, keeping within the video and snapping to silence if asked
+fn pad_dialogue_span(
+    start: f64,
+    end: f64,
+    default_pad: f64,
+    cli: &Cli,
+    ffmpeg: &Path,
+    video_path: &Path,
+    media: &MediaInfo,
+) -> (f64, f64) {
+    if let Some(around) = cli.around {
+        // Fixed-length window centered on the dialogue instead of padding
+        let center = (start + end) / 2.0;
+        let window_start = (center - around / 2.0).max(0.0);
+        println!(
+            "Found dialogue at {:.1}s - {:.1}s ({:.1}s window centered at {:.1}s)",
+            start, end, around, center
+        );
+        return (window_start, clamp_window_end(center + around / 2.0, media));
+    }
+
+    let pad_before = cli.pad_before.or(cli.pad).unwrap_or(default_pad);
+    let pad_after = cli.pad_after.or(cli.pad).unwrap_or(default_pad);
+    let mut start_padded = (start - pad_before).max(0.0);
+    let mut end_padded = end + pad_after;
+
+    println!(
+        "Found dialogue at {:.1}s - {:.1}s (padding: {:.1}s before, {:.1}s after)",
+        start, end, pad_before, pad_after
+    );
+
+    // Lead-in clamped by the start of the video
+    let shortfall = pad_before - (start - start_padded);
+    if shortfall > 0.001 {
+        if cli.balance_padding {
+            end_padded += shortfall;
+            println!(
+                "Note: lead-in truncated to {:.1}s by the start of the video; added {:.1}s to the end instead",
+                start - start_padded,
+                shortfall
+            );
+        } else {
+            println!(
+                "Note: lead-in truncated to {:.1}s by the start of the video (use --balance-padding to add the rest to the end)",
+                start - start_padded
+            );
+        }
+    }
+
+    // Lead-out clamped by the end of the video, where ffmpeg would otherwise just stop short
+    if let Some(media_duration) = media.duration
+        && end_padded > media_duration + 0.001
+    {
+        let shortfall = end_padded - media_duration;
+        end_padded = media_duration;
+        if cli.balance_padding && start_padded > 0.0 {
+            let moved = shortfall.min(start_padded);
+            start_padded -= moved;
+            println!(
+                "Note: lead-out truncated to {:.1}s by the end of the video; added {:.1}s to the start instead",
+                (end_padded - end).max(0.0),
+                moved
+            );
+        } else {
+            println!(
+                "Note: lead-out truncated to {:.1}s by the end of the video at {:.1}s (use --balance-padding to add the rest to the start)",
+                (end_padded - end).max(0.0),
+                media_duration
+            );
+        }
+    }
+
+    if cli.snap_silence {
+        snap_to_silence(ffmpeg, video_path, start, end, start_padded, end_padded)
+    } else {
+        (start_padded, end_padded)
+    }
+}
+
+/// Cut an --around window off at the end of the video, which ffmpeg would otherwise
+/// reach early without a word
+fn clamp_window_end(window_end: f64, media: &MediaInfo) -> f64 {
+    match media.duration {
+        Some(media_duration) if window_end > media_duration + 0.001 => {
+            println!(
+                "Note: window truncated by {:.1}s at the end of the video ({:.1}s)",
+                window_end - media_duration,
+                media_duration
+            );
+            media_duration
+        }
+        _ => window_end,
+    }
+}
+
+fn warn_low_confidence(m: &srt::DialogueMatch, query: &str) {
+    if m.is_low_confidence() {
+        eprintln!(
+            "Warning: low-confidence match ({:.0}%) for \"{}\": \"{}\"",
+            m.score * 100.0,
+            query,
+            m.entry.text
+        );
+    }
+}
+
+/// yt-dlp verbosity flags: silent under --quiet, otherwise one progress update per line
+/// so the output reads cleanly in logs instead of redrawing in place
+fn yt_dlp_output_args(quiet: bool) -> &'static [&'static str] {
+    if quiet {
+        &["--quiet", "--no-progress"]
+    } else {
+        &["--newline"]
+    }
+}
+
+fn download_auto_subs(
+    yt_dlp: &Path,
+    url: &str,
+    lang: &str,
+    video_path: &Path,
+    quiet: bool,
+) -> Result<()> {
+    let status = Command::new(yt_dlp)
+        .arg("--skip-download")
+        .arg("--write-auto-sub")
+        .arg("--sub-lang")
+        .arg(lang)
+        .arg("--convert-subs")
+        .arg("srt")
+        .arg("-o")
+        .arg(video_path)
+        .arg("--no-playlist")
+        .args(yt_dlp_output_args(quiet))
+        .arg(url)
+        .status()
+        .context("Failed to run yt-dlp")?;
+
+    if !status.success() {
+        bail!("yt-dlp failed to download auto-generated captions");
+    }
+
+    Ok(())
+}
+
+/// Fill `{placeholder}`s in an output filename template, then make the result filename-safe
+fn render_output_template(template: &str, vars: &[(&str, String)]) -> Result<String> {
+    let placeholder_re = Regex::new(r"\{([a-z]+)\}").unwrap();
+
+    if let Some(unknown) = placeholder_re
+        .captures_iter(template)
+        .find(|caps| !vars.iter().any(|(name, _)| *name == &caps[1]))
+    {
+        let known: Vec<String> = vars.iter().map(|(name, _)| format!("{{{}}}", name)).collect();
+        bail!(
+            "Unknown placeholder {} in output template (available: {})",
+            &unknown[0],
+            known.join(", ")
+        );
+    }
+
+    let rendered = placeholder_re.replace_all(template, |caps: &regex::Captures| {
+        vars.iter()
+            .find(|(name, _)| *name == &caps[1])
+            .map(|(_, value)| value.clone())
+            .unwrap_or_default()
+    });
+
+    let invalid_re = Regex::new(r#"[<>:"/\\|?*]"#).unwrap();
+    Ok(invalid_re.replace_all(&rendered, "_").into_owned())
+}
+
+/// Longest generated title, in graphemes
+const MAX_TITLE_LEN: usize = 50;
+
+/// Make a video title safe to use in a filename on every platform
+fn sanitize_filename(name: &str) -> String {
+    let invalid_re = Regex::new(r#"[<>:"/\\|?*\x00-\x1f]"#).unwrap();
+    let underscores_re = Regex::new(r"_{2,}").unwrap();
+    let spaces_re = Regex::new(r"\s+").unwrap();
+
+    let sanitized = invalid_re.replace_all(name, "_");
+    let sanitized = underscores_re.replace_all(&sanitized, "_");
+    let sanitized = spaces_re.replace_all(sanitized.trim(), " ");
+
+    let graphemes: Vec<&str> = sanitized.graphemes(true).collect();
+    let truncated = if graphemes.len() > MAX_TITLE_LEN {
+        // Prefer cutting at a word boundary, unless that would throw away most of the title
+        let is_break = |g: &str| g == " " || g == "_" || g == "-";
+        let cut = (MAX_TITLE_LEN / 2..=MAX_TITLE_LEN)
+            .rev()
+            .find(|&i| is_break(graphemes[i]))
+            .unwrap_or(MAX_TITLE_LEN);
+        graphemes[..cut].concat()
+    } else {
+        sanitized.into_owned()
+    };
+
+    // Windows rejects names ending in dots or spaces
+    let trimmed = truncated.trim_end_matches(['.', ' ', '_', '-']).trim_start();
+
+    if trimmed.is_empty() {
+        "video".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+fn format_timestamp(secs: f64) -> String {
+    let mins = (secs / 60.0).floor() as u32;
+    let secs = (secs % 60.0).floor() as u32;
+    format!("{}m{}s", mins, secs)
+}
+
+/// Format seconds as `M:SS` or `H:MM:SS`, matching the accepted timestamp input
+fn format_clock(secs: f64) -> String {
+    let total = secs.max(0.0).floor() as u64;
+    let (hours, mins, secs) = (total / 3600, (total % 3600) / 60, total % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, mins, secs)
+    } else {
+        format!("{}:{:02}", mins, secs)
+    }
+}
+
+fn load_chapters(config: &config::Config, input: &str, media: &Path) -> Result<Vec<chapters::Chapter>> {
+    if is_url(input) && is_youtube_url(input) {
+        chapters::from_yt_dlp(&config.yt_dlp_path()?, input)
+    } else {
+        chapters::from_ffprobe(&config.ffprobe_path()?, media)
+    }
+}
+
+/// Apply text rewrites (--sub-case, --sub-max-chars) to the subtitles being burned, writing the
+/// result to a temp SRT. Styling from ASS input is not carried over.
+fn rewrite_subtitles(sub_path: &Path, cli: &Cli, temp_path: &Path) -> Result<PathBuf> {
+    let mut entries = srt::merge_held_captions(srt::parse_subtitles(sub_path)?);
+    rewrite_entries(&mut entries, cli);
+
+    let rewritten = temp_path.join("rewritten.srt");
+    srt::write_srt(&entries, &rewritten)?;
+    Ok(rewritten)
+}
+
+fn rewrite_entries(entries: &mut [srt::SubtitleEntry], cli: &Cli) {
+    for entry in entries {
+        match cli.sub_case {
+            SubCase::None => {}
+            SubCase::Upper => entry.text = entry.text.to_uppercase(),
+            SubCase::Lower => entry.text = entry.text.to_lowercase(),
+            SubCase::Title => entry.text = title_case(&entry.text),
+        }
+        if let Some(max_chars) = cli.sub_max_chars {
+            entry.text = srt::wrap_text(&entry.text, max_chars as usize);
+        }
+    }
+}
+
+/// Capitalize the first letter of each word and lowercase the rest
+fn title_case(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut word_start = true;
+    for c in text.chars() {
+        if word_start {
+            out.extend(c.to_uppercase());
+        } else {
+            out.extend(c.to_lowercase());
+        }
+        word_start = c.is_whitespace();
+    }
+    out
+}
+
+/// Background box behind subtitles for --sub-box
+struct SubBox {
+    color: Rgb,
+    opacity: f64,
+}
+
+impl SubBox {
+    fn from_cli(cli: &Cli) -> Option<Self> {
+        cli.sub_box.then_some(SubBox {
+            color: cli.sub_box_color,
+            opacity: cli.sub_box_opacity,
+        })
+    }
+
+    /// ASS style fields: an opaque box (BorderStyle=3) drawn in the outline colour, with
+    /// the shadow colour matched for renderers that use that instead
+    fn ass_style(&self) -> String {
+        let Rgb(r, g, b) = self.color;
+        // ASS colours are &HAABBGGRR with alpha counting up from 00 (opaque)
+        let alpha = ((1.0 - self.opacity) * 255.0).round() as u8;
+        let colour = format!("&H{:02X}{:02X}{:02X}{:02X}", alpha, b, g, r);
+        format!("BorderStyle=3,Outline=1,Shadow=0,OutlineColour={c},BackColour={c}", c = colour)
+    }
+
+    /// drawtext box options for --text
+    fn drawtext_options(&self) -> String {
+        let Rgb(r, g, b) = self.color;
+        format!(":box=1:boxcolor=0x{:02X}{:02X}{:02X}@{}:boxborderw=6", r, g, b, self.opacity)
+    }
+}
+
+fn build_subtitle_filter(
+    sub_path: &Option<PathBuf>,
+    custom_text: &Option<String>,
+    sub_style: &Option<String>,
+    sub_box: Option<&SubBox>,
+    on_top: bool,
+) -> Option<String> {
+    // Custom text takes priority over subtitle file
+    if let Some(text) = custom_text {
+        let text_escaped = text
+            .replace('\\', "\\\\")
+            .replace(':', "\\:")
+            .replace("'", "\\'");
+        // drawtext filter with bottom-center positioning, white text with black outline
+        let y = if on_top { "20" } else { "h-th-20" };
+        return Some(format!(
+            "drawtext=text='{}':fontsize=24:fontcolor=white:borderw=2:bordercolor=black:x=(w-text_w)/2:y={}{}",
+            text_escaped,
+            y,
+            sub_box.map(SubBox::drawtext_options).unwrap_or_default()
+        ));
+    }
+
+    sub_path.as_ref().map(|subs| {
+        // ASS numpad alignment 8 is top-center; a user --sub-style comes last so it wins
+        let box_style = sub_box.map(SubBox::ass_style);
+        let style: Vec<&str> = on_top
+            .then_some("Alignment=8")
+            .into_iter()
+            .chain(box_style.as_deref())
+            .chain(sub_style.as_deref().filter(|s| !s.is_empty()))
+            .collect();
+
+        let mut filter = format!("subtitles='{}'", escape_filter_path(subs));
+        if !style.is_empty() {
+            filter.push_str(&format!(":force_style='{}'", style.join(",")));
+        }
+        filter
+    })
+}
+
+/// Escape a file path for use as a quoted ffmpeg filter option value
+fn escape_filter_path(path: &Path) -> String {
+    path.to_string_lossy()
+        .replace('\\', "\\\\")
+        .replace(':', "\\:")
+        .replace("'", "\\'")
+}
+
+/// Final output size for a source frame, following the scale, --blur-pad and --pad-to steps
+fn output_dimensions(cli: &Cli, source: (u32, u32)) -> (u32, u32) {
+    if let Some(aspect) = cli.blur_pad {
+        return (cli.width, (cli.width * aspect.height / aspect.width / 2 * 2).max(2));
+    }
+
+    let (src_w, src_h) = (source.0.max(1) as f64, source.1.max(1) as f64);
+    let (w, h) = match cli.height {
+        Some(height) => ((height as f64 * src_w / src_h).round(), height as f64),
+        None => (cli.width as f64, (cli.width as f64 * src_h / src_w).round()),
+    };
+
+    match cli.pad_to {
+        Some(aspect) => {
+            let (aw, ah) = (aspect.width as f64, aspect.height as f64);
+            let even = |x: f64| (x / 2.0).trunc() as u32 * 2;
+            (even(w.max(h * aw / ah)), even(h.max(w * ah / aw)))
+        }
+        None => (w as u32, h as u32),
+    }
+}
+
+fn build_pad_filter(cli: &Cli) -> Option<String> {
+    let aspect = cli.pad_to?;
+    let (w, h) = (aspect.width, aspect.height);
+    // Grow whichever side is short of the target ratio, rounded to even sizes for the video codecs
+    Some(format!(
+        "pad=w='trunc(max(iw,ih*{w}/{h})/2)*2':h='trunc(max(ih,iw*{h}/{w})/2)*2':x='(ow-iw)/2':y='(oh-ih)/2':color={}",
+        cli.pad_color
+    ))
+}
+
+/// How far (seconds) --seamless may move each boundary
+const SEAMLESS_WINDOW: f64 = 0.5;
+/// Side length of the grayscale thumbnails compared by --seamless
+const SEAMLESS_THUMB: usize = 32;
+
+/// Search around both ends of the clip for the pair of frames that look most alike,
+/// so the last frame flows back into the first when the GIF loops.
+fn find_seamless_loop(
+    ffmpeg: &Path,
+    video_path: &Path,
+    start: f64,
+    end: f64,
+    fps: u32,
+) -> Result<(f64, f64)> {
+    println!("Searching for a seamless loop...");
+
+    let start_frames = sample_frames(
+        ffmpeg,
+        video_path,
+        (start - SEAMLESS_WINDOW).max(0.0),
+        start + SEAMLESS_WINDOW,
+        fps,
+    )?;
+    let end_frames = sample_frames(
+        ffmpeg,
+        video_path,
+        (end - SEAMLESS_WINDOW).max(0.0),
+        end + SEAMLESS_WINDOW,
+        fps,
+    )?;
+
+    // Don't let the search collapse the clip to nothing
+    let min_duration = (end - start) / 2.0;
+
+    let mut best: Option<(f64, f64, f64)> = None;
+    for (start_t, start_pixels) in &start_frames {
+        for (end_t, end_pixels) in &end_frames {
+            if end_t - start_t < min_duration {
+                continue;
+            }
+            let diff = frame_difference(start_pixels, end_pixels);
+            if best.is_none_or(|(_, _, best_diff)| diff < best_diff) {
+                best = Some((*start_t, *end_t, diff));
+            }
+        }
+    }
+
+    let (loop_start, loop_end, diff) =
+        best.context("Could not sample frames to find a seamless loop")?;
+
+    println!(
+        "Seamless loop: {:.2}s - {:.2}s (was {:.2}s - {:.2}s, frame difference {:.1}%)",
+        loop_start,
+        loop_end,
+        start,
+        end,
+        diff / 255.0 * 100.0
+    );
+
+    Ok((loop_start, loop_end))
+}
+
+/// Decode a time range as small grayscale thumbnails, returning (source time, pixels)
+fn sample_frames(
+    ffmpeg: &Path,
+    video_path: &Path,
+    from: f64,
+    to: f64,
+    fps: u32,
+) -> Result<Vec<(f64, Vec<u8>)>> {
+    let output = Command::new(ffmpeg)
+        .arg("-ss")
+        .arg(format!("{}", from))
+        .arg("-t")
+        .arg(format!("{}", to - from))
+        .arg("-i")
+        .arg(video_path)
+        .arg("-vf")
+        .arg(format!("fps={},scale={}:{},format=gray", fps, SEAMLESS_THUMB, SEAMLESS_THUMB))
+        .arg("-f")
+        .arg("rawvideo")
+        .arg("-")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .context("Failed to run ffmpeg for frame sampling")?;
+
+    if !output.status.success() {
+        bail!("ffmpeg failed to sample frames");
+    }
+
+    Ok(output
+        .stdout
+        .chunks_exact(SEAMLESS_THUMB * SEAMLESS_THUMB)
+        .enumerate()
+        .map(|(i, pixels)| (from + i as f64 / fps as f64, pixels.to_vec()))
+        .collect())
+}
+
+/// Run cropdetect over the clip and return the crop it settles on most often, or None if
+/// it keeps the whole frame
+fn detect_black_bars(
+    ffmpeg: &Path,
+    video_path: &Path,
+    source: (u32, u32),
+    rotation: Option<u32>,
+    from: f64,
+    to: f64,
+) -> Result<Option<Crop>> {
+    println!("Detecting black bars...");
+
+    let mut cmd = Command::new(ffmpeg);
+    if rotation.is_some() {
+        cmd.arg("-noautorotate");
+    }
+    let output = cmd
+        .arg("-ss")
+        .arg(format!("{}", from))
+        .arg("-t")
+        .arg(format!("{}", to - from))
+        .arg("-i")
+        .arg(video_path)
+        .arg("-vf")
+        .arg(oriented_filter(rotation, "fps=2,cropdetect=limit=24:round=2:reset=0"))
+        .arg("-f")
+        .arg("null")
+        .arg("-")
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .context("Failed to run ffmpeg cropdetect")?;
+
+    if !output.status.success() {
+        bail!("ffmpeg cropdetect failed");
+    }
+
+    let crop_re = Regex::new(r"crop=(\d+):(\d+):(\d+):(\d+)").unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for found in crop_re.find_iter(&stderr) {
+        match counts.iter_mut().find(|(crop, _)| *crop == found.as_str()) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((found.as_str(), 1)),
+        }
+    }
+
+    let Some((best, _)) = counts.iter().max_by_key(|(_, count)| *count) else {
+        return Ok(None);
+    };
+    let crop = parse_crop(best.trim_start_matches("crop=")).map_err(anyhow::Error::msg)?;
+
+    let whole_frame = crop.width >= source.0 && crop.height >= source.1;
+    Ok((!whole_frame).then_some(crop))
+}
+
+/// Mean luma (0-255) of the bottom third above which subtitles move to the top
+const BUSY_STRIP_LUMA: f64 = 160.0;
+/// Mean horizontal luma change in the bottom third above which it counts as busy (text, credits)
+const BUSY_STRIP_DETAIL: f64 = 24.0;
+
+/// Whether the bottom third of the clip is bright or detailed enough that burned-in
+/// subtitles there would be hard to read or collide with existing captions
+fn bottom_strip_is_busy(ffmpeg: &Path, video_path: &Path, from: f64, to: f64) -> Result<bool> {
+    let frames = sample_frames(ffmpeg, video_path, from, to, 2)?;
+    if frames.is_empty() {
+        return Ok(false);
+    }
+
+    let strip_start = SEAMLESS_THUMB * 2 / 3;
+    let (mut luma, mut detail) = (0.0, 0.0);
+    for (_, pixels) in &frames {
+        let rows = pixels[strip_start * SEAMLESS_THUMB..].chunks_exact(SEAMLESS_THUMB);
+        let count = (rows.len() * SEAMLESS_THUMB).max(1) as f64;
+        let (mut sum, mut edges) = (0u64, 0u64);
+        for row in rows {
+            sum += row.iter().map(|&p| p as u64).sum::<u64>();
+            edges += row.windows(2).map(|w| w[0].abs_diff(w[1]) as u64).sum::<u64>();
+        }
+        luma += sum as f64 / count;
+        detail += edges as f64 / count;
+    }
+
+    let n = frames.len() as f64;
+    Ok(luma / n > BUSY_STRIP_LUMA || detail / n > BUSY_STRIP_DETAIL)
+}
+
+/// Mean absolute pixel difference between two thumbnails (0-255)
+fn frame_difference(a: &[u8], b: &[u8]) -> f64 {
+    let total: u64 = a.iter().zip(b).map(|(x, y)| x.abs_diff(*y) as u64).sum();
+    total as f64 / a.len().max(1) as f64
+}
+
+/// Files prepared during the run that the video filters read from
+#[derive(Clone, Default)]
+struct FilterAssets {
+    /// Earliest source time the filter graph reads, where input seeking can jump to
+    source_start: f64,
+    /// Clockwise turn applied in the graph, with ffmpeg's autorotate off
+    rotation: Option<u32>,
+    /// Convert HDR to SDR first (--tonemap on an HDR source)
+    tonemap: bool,
+    /// --crop, or the bars found by --trim-black
+    crop: Option<Crop>,
+    sub_path: Option<PathBuf>,
+    /// Subtitles to mux as a track (--soft-subs) rather than burn
+    soft_subs: Option<PathBuf>,
+    /// Place subtitles/text at the top instead of the bottom (--smart-sub-position)
+    subs_on_top: bool,
+    /// vidstabdetect results for --stabilize
+    stabilize_transforms: Option<PathBuf>,
+    /// Source (start, end) ranges joined into one output; empty for a single clip
+    supercut_segments: Vec<(f64, f64)>,
+    /// Trim + setpts chain for --speed-ramp
+    speed_ramp_filter: Option<String>,
+    /// Trim + tempo chain for --audio with --speed
+    audio_filter: Option<String>,
+    /// Audio stream chosen with --audio-track/--audio-lang, if any
+    audio_track: Option<usize>,
+    /// Source frame size and clip range for --intro/--outro cards
+    cards: Option<CardLayout>,
+    /// Black or held frames at both ends for --pad-black/--pad-hold
+    edge_pad: Option<EdgePad>,
+}
+
+#[derive(Clone)]
+struct EdgePad {
+    seconds: f64,
+    /// Repeat the first/last frame rather than adding black
+    hold: bool,
+    /// Source range to cut the clip to first, unless the graph already trims and retimes it
+    trim: Option<(f64, f64)>,
+}
+
+/// Cards are fitted to the source frame size and concatenated before scaling, so the
+/// rest of the filter chain treats them like any other frames
+#[derive(Clone)]
+struct CardLayout {
+    width: u32,
+    height: u32,
+    /// Range of the main clip's stream to keep, in its own timestamps
+    clip_start: f64,
+    clip_end: f64,
+}
+
+/// Speed keyframes for --speed-ramp: from each clip-relative time on, play at that speed
+#[derive(Debug, Clone, PartialEq)]
+struct SpeedRamp {
+    keyframes: Vec<(f64, f64)>,
+}
+
+/// A still image shown before or after the clip (--intro/--outro)
+#[derive(Debug, Clone)]
+struct Card {
+    image: PathBuf,
+    seconds: f64,
+}
+
+fn parse_card(s: &str) -> Result<Card, String> {
+    // Split on the last colon so Windows paths like C:\logo.png still work
+    let (image, seconds) = s
+        .rsplit_once(':')
+        .ok_or_else(|| format!("Invalid card '{}'. Use IMAGE:SECONDS, e.g. logo.png:0.5", s))?;
+    let seconds: f64 = seconds
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid card duration '{}'", seconds))?;
+    if seconds <= 0.0 {
+        return Err(format!("Card duration must be positive, got {}", seconds));
+    }
+    Ok(Card {
+        image: PathBuf::from(image),
+        seconds,
+    })
+}
+
+fn parse_speed(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(speed) if speed > 0.0 => Ok(speed),
+        _ => Err(format!("Invalid speed '{}'. Use a positive factor, e.g. 0.5 or 2", s)),
+    }
+}
+
+fn parse_speed_ramp(s: &str) -> Result<SpeedRamp, String> {
+    let mut keyframes = Vec::new();
+
+    for part in s.split(',') {
+        let (time, speed) = part
+            .trim()
+            .split_once(':')
+            .ok_or_else(|| format!("Invalid speed keyframe '{}'. Use TIME:SPEED, e.g. 1.5:0.25", part))?;
+        let time: f64 = time.trim().parse().map_err(|_| format!("Invalid keyframe time '{}'", time))?;
+        let speed: f64 = speed.trim().parse().map_err(|_| format!("Invalid keyframe speed '{}'", speed))?;
+
+        if time < 0.0 || speed <= 0.0 {
+            return Err(format!("Keyframe '{}' needs a time >= 0 and a speed > 0", part));
+        }
+        if keyframes.last().is_some_and(|(last, _)| time <= *last) {
+            return Err("Speed keyframe times must be strictly increasing".to_string());
+        }
+
+        keyframes.push((time, speed));
+    }
+
+    Ok(SpeedRamp { keyframes })
+}
+
+/// Trim the clip inside the graph and remap timestamps piecewise so each keyframe's speed
+/// applies until the next one. Returns the filter and the retimed clip length.
+fn build_speed_ramp_filter(ramp: &SpeedRamp, start: f64, end: f64) -> Result<(String, f64)> {
+    let clip_len = end - start;
+    if let Some((time, _)) = ramp.keyframes.iter().find(|(t, _)| *t >= clip_len) {
+        bail!("Speed keyframe at {}s is outside the {:.1}s clip", time, clip_len);
+    }
+
+    // Segments of constant speed; before the first keyframe the clip plays at normal speed
+    let mut segments = vec![(0.0, 1.0)];
+    segments.extend(ramp.keyframes.iter().copied());
+
+    // Output time at which each segment starts
+    let mut offsets = Vec::with_capacity(segments.len());
+    let mut out_time = 0.0;
+    for (i, (seg_start, speed)) in segments.iter().enumerate() {
+        offsets.push(out_time);
+        let seg_end = segments.get(i + 1).map_or(clip_len, |(t, _)| *t);
+        out_time += (seg_end - seg_start) / speed;
+    }
+
+    // Nest if(lt(T,next),this,rest) from the last segment backwards
+    let mut expr = String::new();
+    for (i, ((seg_start, speed), offset)) in segments.iter().zip(&offsets).enumerate().rev() {
+        let piece = format!("{}+(T-{})/{}", offset, seg_start, speed);
+        expr = if i == segments.len() - 1 {
+            piece
+        } else {
+            format!("if(lt(T,{}),{},{})", segments[i + 1].0, piece, expr)
+        };
+    }
+
+    Ok((
+        format!(
+            "trim=start={}:end={},setpts=PTS-STARTPTS,setpts='({})/TB'",
+            start, end, expr
+        ),
+        out_time,
+    ))
+}
+
+/// Trim the audio to the clip and change its speed to match `--speed`. Without
+/// `preserve_pitch` the audio is resampled like a tape played faster or slower;
+/// with it, the tempo changes and the pitch stays put.
+fn build_audio_speed_filter(
+    config: &config::Config,
+    media: &MediaInfo,
+    track: usize,
+    speed: f64,
+    preserve_pitch: bool,
+    start: f64,
+    end: f64,
+) -> Result<String> {
+    let trim = format!("atrim=start={}:end={},asetpts=PTS-STARTPTS", start, end);
+
+    if !preserve_pitch {
+        let rate = get_audio_sample_rate(media, track)?;
+        return Ok(format!("{},asetrate={},aresample={}", trim, (rate as f64 * speed).round(), rate));
+    }
+
+    if ffmpeg_lists(&config.ffmpeg_path()?, "-filters", "rubberband") {
+        return Ok(format!("{},rubberband=tempo={}", trim, speed));
+    }
+
+    // atempo only takes 0.5-2.0 in older ffmpeg builds, so chain factors within that range
+    let mut tempo = Vec::new();
+    let mut remaining = speed;
+    while remaining < 0.5 {
+        tempo.push("atempo=0.5".to_string());
+        remaining /= 0.5;
+    }
+    while remaining > 2.0 {
+        tempo.push("atempo=2.0".to_string());
+        remaining /= 2.0;
+    }
+    tempo.push(format!("atempo={}", remaining));
+
+    Ok(format!("{},{}", trim, tempo.join(",")))
+}
+
+/// Loudness target for --normalize-audio: -16 LUFS integrated, as most streaming
+/// and social platforms use
+const LOUDNORM_TARGET: &str = "I=-16:TP=-1.5:LRA=11";
+
+/// First loudnorm pass over the clip's audio. Returns the second-pass filter with the
+/// measured values filled in, so the gain is applied linearly instead of guessed on the fly.
+/// None (with a warning) when ffmpeg has no loudnorm filter.
+fn measure_loudness(
+    ffmpeg: &Path,
+    video_path: &Path,
+    track: usize,
+    start: f64,
+    duration: f64,
+) -> Result<Option<String>> {
+    if !ffmpeg_lists(ffmpeg, "-filters", "loudnorm") {
+        eprintln!("Warning: This ffmpeg has no loudnorm filter; skipping --normalize-audio");
+        return Ok(None);
+    }
+
+    println!("Measuring loudness...");
+    let output = Command::new(ffmpeg)
+        .arg("-hide_banner")
+        .arg("-ss")
+        .arg(format!("{}", start))
+        .arg("-t")
+        .arg(format!("{}", duration))
+        .arg("-i")
+        .arg(video_path)
+        .arg("-map")
+        .arg(format!("0:a:{}", track))
+        .arg("-af")
+        .arg(format!("loudnorm={}:print_format=json", LOUDNORM_TARGET))
+        .arg("-f")
+        .arg("null")
+        .arg("-")
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .context("Failed to run ffmpeg for loudness analysis")?;
+
+    // The measurements are the last JSON object on stderr
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let measured: Option<serde_json::Map<String, serde_json::Value>> = stderr
+        .rfind('{')
+        .and_then(|open| Some((open, stderr[open..].find('}')?)))
+        .and_then(|(open, close)| serde_json::from_str(&stderr[open..=open + close]).ok());
+    let value = |key: &str| measured.as_ref()?.get(key)?.as_str().map(str::to_string);
+
+    let (Some(i), Some(tp), Some(lra), Some(thresh), Some(offset)) = (
+        value("input_i"),
+        value("input_tp"),
+        value("input_lra"),
+        value("input_thresh"),
+        value("target_offset"),
+    ) else {
+        bail!("Couldn't measure the clip's loudness (does the video have sound?)");
+    };
+    // Silence measures as -inf, which loudnorm can't take back
+    if [&i, &tp, &thresh].iter().any(|v| v.contains("inf")) {
+        eprintln!("Warning: The clip's audio is silent; skipping --normalize-audio");
+        return Ok(None);
+    }
+
+    println!("Loudness: {} LUFS, normalizing to -16 LUFS", i);
+    // loudnorm works (and outputs) at 192 kHz; bring it back to a rate every codec takes
+    Ok(Some(format!(
+        "loudnorm={}:measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true,\
+         aresample=48000",
+        LOUDNORM_TARGET, i, tp, lra, thresh, offset
+    )))
+}
+
+/// Whether this ffmpeg build lists `name` under `flag` (`-encoders` or `-filters`), for
+/// optional pieces like the rubberband filter or the libvpx encoder
+fn ffmpeg_lists(ffmpeg: &Path, flag: &str, name: &str) -> bool {
+    Command::new(ffmpeg)
+        .args(["-hide_banner", flag])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .is_ok_and(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .any(|line| line.split_whitespace().nth(1) == Some(name))
+        })
+}
+
+/// Split the stream, trim each supercut segment and concatenate them in order.
+/// Runs after subtitles so each segment keeps its own burned-in lines.
+fn build_supercut_filter(segments: &[(f64, f64)]) -> String {
+    let n = segments.len();
+    let split_labels: String = (0..n).map(|i| format!("[sc{}]", i)).collect();
+    let concat_labels: String = (0..n).map(|i| format!("[sct{}]", i)).collect();
+
+    let mut graph = format!("split={}{}", n, split_labels);
+    for (i, (start, end)) in segments.iter().enumerate() {
+        graph.push_str(&format!(
+            ";[sc{i}]trim=start={start}:end={end},setpts=PTS-STARTPTS[sct{i}]"
+        ));
+    }
+    graph.push_str(&format!(";{}concat=n={}:v=1:a=0", concat_labels, n));
+    graph
+}
+
+/// First stabilization pass: analyse camera shake over the clip with vidstabdetect.
+///
+/// Seeks the same way as the encoders (-ss after -i) so the transform file's frame
+/// numbering matches what vidstabtransform sees in the final encode.
+fn detect_stabilization(
+    ffmpeg: &Path,
+    video_path: &Path,
+    temp_path: &Path,
+    rotation: Option<u32>,
+    start_secs: f64,
+    duration: f64,
+) -> Result<PathBuf> {
+    if !ffmpeg_lists(ffmpeg, "-filters", "vidstabdetect") {
+        bail!(
+            "--stabilize needs an ffmpeg built with libvidstab (--enable-libvidstab). \
+             The managed ffmpeg builds and most distro packages include it."
+        );
+    }
+
+    println!("Analysing camera motion...");
+    let transforms = temp_path.join("transforms.trf");
+
+    let mut cmd = Command::new(ffmpeg);
+    cmd.arg("-y");
+    if rotation.is_some() {
+        cmd.arg("-noautorotate");
+    }
+    let detect = format!(
+        "vidstabdetect=shakiness=5:accuracy=15:result='{}'",
+        escape_filter_path(&transforms)
+    );
+    let status = cmd
+        .arg("-i")
+        .arg(video_path)
+        .arg("-ss")
+        .arg(format!("{}", start_secs))
+        .arg("-t")
+        .arg(format!("{}", duration))
+        .arg("-vf")
+        .arg(oriented_filter(rotation, &detect))
+        .arg("-f")
+        .arg("null")
+        .arg("-")
+        .stderr(Stdio::null())
+        .status()
+        .context("Failed to run ffmpeg for stabilization")?;
+
+    if !status.success() {
+        bail!("ffmpeg failed to analyse the clip for stabilization");
+    }
+
+    Ok(transforms)
+}
+
+/// Blur-pad composition: a blurred, zoomed copy of the clip fills the frame behind the sharp original
+fn build_blur_pad_filter(width: u32, aspect: AspectRatio, scale_flags: &str) -> String {
+    let height = (width * aspect.height / aspect.width / 2 * 2).max(2);
+    format!(
+        "split[bg][fg];\
+         [bg]scale={w}:{h}:force_original_aspect_ratio=increase{f},crop={w}:{h},boxblur=20:2[bgblur];\
+         [fg]scale={w}:{h}:force_original_aspect_ratio=decrease{f}[fgscaled];\
+         [bgblur][fgscaled]overlay=x=(W-w)/2:y=(H-h)/2",
+        w = width,
+        h = height,
+        f = scale_flags
+    )
+}
+
+/// Mask the corners to a radius. WebM keeps true alpha; GIF (1-bit transparency) and MP4
+/// (no alpha) get the corners filled with `--corner-color` instead, unless chroma keying
+/// already made the GIF transparent.
+fn build_corner_filter(cli: &Cli, format: OutputFormat) -> Option<String> {
+    let r = cli.rounded_corners?;
+    let mask = format!(
+        "format=rgba,geq=r='r(X,Y)':g='g(X,Y)':b='b(X,Y)':\
+         a='if(gt(pow(max(max({r}-X,X-(W-1-{r})),0),2)+pow(max(max({r}-Y,Y-(H-1-{r})),0),2),{r}*{r}),0,alpha(X,Y))'",
+        r = r
+    );
+
+    // Keyed output is already transparent, so leave the corners transparent too
+    if matches!(format, OutputFormat::Webm | OutputFormat::Webp) || cli.chroma_key.is_some() {
+        return Some(mask);
+    }
+
+    Some(format!(
+        "{},split[rc][rcbg];[rcbg]drawbox=c={}:t=fill[rcfill];[rcfill][rc]overlay",
+        mask, cli.corner_color
+    ))
+}
+
+/// Scaler for MP4, WebM and WebP when --scale-flags isn't given
+const VIDEO_SCALE_FLAGS: &str = "bilinear";
+
+/// Build the video filter graph shared by all encoders: subtitles, fps, scaling, padding.
+/// `default_scale_flags` is the format's scaler when --scale-flags isn't given.
+fn build_video_filter(
+    cli: &Cli,
+    assets: &FilterAssets,
+    format: OutputFormat,
+    default_scale_flags: &str,
+) -> String {
+    let flags = match cli.scale_flags {
+        Some(f) => format!(":flags={}", format!("{:?}", f).to_lowercase()),
+        None => format!(":flags={}", default_scale_flags),
+    };
+
+    let mut filters = Vec::new();
+
+    // Turn the frames upright first; the analysis passes do the same
+    if let Some(rotate) = assets.rotation.and_then(rotation_filter) {
+        filters.push(rotate.to_string());
+    }
+
+    // Before the other filters so frames line up with the vidstabdetect pass
+    if let Some(ref transforms) = assets.stabilize_transforms {
+        filters.push(format!(
+            "vidstabtransform=input='{}':smoothing=10:zoom=0:optzoom=1",
+            escape_filter_path(transforms)
+        ));
+    }
+
+    // Tone-map before anything draws on the frame, so subtitles keep their SDR colors.
+    // Land in the output's pixel format so a 10-bit --pix-fmt keeps its precision
+    if assets.tonemap {
+        filters.push(format!(
+            "zscale=t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,\
+             tonemap=hable:desat=0,zscale=t=bt709:m=bt709:r=tv,format={}",
+            cli.pix_fmt
+        ));
+    }
+
+    if let Some(ref prepend) = cli.vf_prepend {
+        filters.push(prepend.clone());
+    }
+
+    // Before subtitles, so lines aren't drawn into bars that are about to be cropped away
+    if let Some(crop) = assets.crop {
+        filters.push(format!("crop={}:{}:{}:{}", crop.width, crop.height, crop.x, crop.y));
+    }
+
+    // Subtitles draw on every source frame after the crop, ahead of the fps decimation and
+    // the scale below (every format shares this chain), so a low --fps keeps each output
+    // frame showing the cue active at its own time
+    let sub_box = SubBox::from_cli(cli);
+    if let Some(sub_filter) =
+        build_subtitle_filter(&assets.sub_path, &cli.text, &cli.sub_style, sub_box.as_ref(), assets.subs_on_top)
+    {
+        filters.push(sub_filter);
+    }
+
+    if !assets.supercut_segments.is_empty() {
+        filters.push(build_supercut_filter(&assets.supercut_segments));
+    }
+
+    if let Some(ref ramp) = assets.speed_ramp_filter {
+        filters.push(ramp.clone());
+
+        // Synthesize frames at the output rate instead of repeating the slowed-down ones
+        if cli.smooth {
+            let mut interpolate = format!(
+                "minterpolate=fps={}:mi_mode={}",
+                cli.fps,
+                format!("{:?}", cli.smooth_mode).to_lowercase()
+            );
+            if cli.smooth_mode == InterpolationMode::Mci {
+                interpolate.push_str(&format!(
+                    ":mc_mode={}",
+                    format!("{:?}", cli.smooth_compensation).to_lowercase()
+                ));
+            }
+            filters.push(interpolate);
+        }
+    }
+
+    // Everything above works on source frames; with cards, concat them in here
+    let mut graph_prefix = String::new();
+    if let Some(ref cards) = assets.cards {
+        filters.push(format!(
+            "trim=start={}:end={},setpts=PTS-STARTPTS,setsar=1",
+            cards.clip_start, cards.clip_end
+        ));
+        graph_prefix = build_card_graph(cli, cards, &filters.join(","));
+        filters.clear();
+    }
+
+    if let Some(EdgePad { trim: Some((start, end)), .. }) = assets.edge_pad {
+        filters.push(format!("trim=start={}:end={},setpts=PTS-STARTPTS", start, end));
+    }
+
+    filters.push(format!("fps={}", cli.fps));
+
+    if let Some(ref pad) = assets.edge_pad {
+        let mode = if pad.hold { "clone" } else { "add" };
+        filters.push(format!(
+            "tpad=start_duration={s}:stop_duration={s}:start_mode={m}:stop_mode={m}:color=black",
+            s = pad.seconds,
+            m = mode
+        ));
+    }
+
+    if let Some(aspect) = cli.blur_pad {
+        filters.push(build_blur_pad_filter(cli.width, aspect, &flags));
+    } else {
+        match cli.height {
+            Some(height) => filters.push(format!("scale=-1:{}{}", height, flags)),
+            None => filters.push(format!("scale={}:-1{}", cli.width, flags)),
+        }
+    }
+
+    if let Some(ref color) = cli.chroma_key {
+        filters.push(format!(
+            "format=rgba,colorkey={}:{}:{}",
+            color, cli.chroma_similarity, cli.chroma_blend
+        ));
+    }
+
+    if let Some(pad_filter) = build_pad_filter(cli) {
+        filters.push(pad_filter);
+    }
+
+    if cli.burn_timestamp {
+        // Frames keep source timestamps whatever the seek mode, so pts is the real source time,
+        // except after the card and edge-pad trims, which retime the clip to start at 0.
+        // Drawn top-left in monospace to stay clear of bottom-centered subtitles.
+        let offset = match source_time_offset(cli, assets) {
+            0.0 => String::new(),
+            offset => format!("\\:{}", offset),
+        };
+        filters.push(format!(
+            "drawtext=text='%{{pts\\:hms{}}}':font=monospace:fontsize=16:fontcolor=yellow:\
+             box=1:boxcolor=black@0.6:boxborderw=4:x=8:y=8",
+            offset
+        ));
+    }
+
+    if let Some(corner_filter) = build_corner_filter(cli, format) {
+        filters.push(corner_filter);
+    }
+
+    if let Some(ref append) = cli.vf_append {
+        filters.push(append.clone());
+    }
+
+    format!("{}{}", graph_prefix, filters.join(","))
+}
+
+/// What to add to an output frame's pts to get back its source time, when the graph's trims
+/// have retimed the clip: it starts at 0, after the intro card and any leading edge frames
+fn source_time_offset(cli: &Cli, assets: &FilterAssets) -> f64 {
+    let clip_start = match (&assets.cards, &assets.edge_pad) {
+        (Some(cards), _) => cards.clip_start - cli.intro.as_ref().map_or(0.0, |card| card.seconds),
+        (None, Some(EdgePad { trim: Some((start, _)), .. })) => *start,
+        _ => return 0.0,
+    };
+    clip_start - assets.edge_pad.as_ref().map_or(0.0, |pad| pad.seconds)
+}
+
+/// Fit each card image into the source frame (padded with --pad-color) and concat
+/// intro, clip and outro. Returns the graph up to and including the concat, ready for
+/// the rest of the chain to be appended.
+fn build_card_graph(cli: &Cli, cards: &CardLayout, clip_chain: &str) -> String {
+    let fit = format!(
+        "scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2:color={c},setsar=1",
+        w = cards.width,
+        h = cards.height,
+        c = cli.pad_color
     );
 
-    let has_subs = !skip_subs && sub_path.is_some();
-    if !skip_subs && !has_subs && cli.text.is_none() {
-        eprintln!("Warning: No subtitles found, proceeding without them");
+    let mut graph = format!("[0:v]{}[clip];", clip_chain);
+    let mut segments = String::new();
+    let mut input = 1;
+    if cli.intro.is_some() {
+        graph.push_str(&format!("[{}:v]{}[intro];", input, fit));
+        segments.push_str("[intro]");
+        input += 1;
+    }
+    segments.push_str("[clip]");
+    if cli.outro.is_some() {
+        graph.push_str(&format!("[{}:v]{}[outro];", input, fit));
+        segments.push_str("[outro]");
     }
 
-    // Determine output path
-    let output_path = match &cli.output {
-        Some(p) => p.clone(),
-        None => {
-            let safe_title = sanitize_filename(&video_title);
-            let ext = match cli.format {
-                OutputFormat::Gif => "gif",
-                OutputFormat::Webm => "webm",
-                OutputFormat::Mp4 => "mp4",
-            };
-            PathBuf::from(format!(
-                "{}_{}-{}.{}",
-                safe_title,
-                format_timestamp(start_secs),
-                format_timestamp(end_secs),
-                ext
-            ))
+    let n = segments.matches('[').count();
+    format!("{}{}concat=n={}:v=1:a=0,", graph, segments, n)
+}
+
+/// Seconds before the clip that --seek-accuracy hybrid jumps to before decoding exactly
+const HYBRID_PREROLL: f64 = 5.0;
+
+/// Input options for the main video, placed just before its `-i`. Timestamps are kept
+/// (`-copyts`, rebased like ffmpeg normally does with `-start_at_zero`) so subtitles and
+/// in-graph trims still see source time after the jump.
+fn add_input_seek(cmd: &mut Command, cli: &Cli, assets: &FilterAssets) {
+    let seek_to = match effective_seek(cli, assets) {
+        SeekAccuracy::Accurate => return,
+        SeekAccuracy::Hybrid => (assets.source_start - HYBRID_PREROLL).max(0.0),
+        SeekAccuracy::Fast => {
+            cmd.arg("-noaccurate_seek");
+            assets.source_start
         }
     };
+    cmd.arg("-ss")
+        .arg(format!("{}", seek_to))
+        .arg("-copyts")
+        .arg("-start_at_zero");
+}
 
-    // Build and run ffmpeg
-    println!("Generating {}...", output_path.display());
-
-    match cli.format {
-        OutputFormat::Gif => encode_gif(&ffmpeg, &video_path, &output_path, &sub_path, &cli, start_secs, duration)?,
-        OutputFormat::Webm => encode_webm(&ffmpeg, &video_path, &output_path, &sub_path, &cli, start_secs, duration)?,
-        OutputFormat::Mp4 => encode_mp4(&ffmpeg, &video_path, &output_path, &sub_path, &cli, start_secs, duration)?,
+/// Output-side trim to the encode range. Fast seeking keeps whatever the keyframe jump
+/// landed on and just rebases the timestamps to zero.
+fn add_output_seek(cmd: &mut Command, cli: &Cli, assets: &FilterAssets, start_secs: f64, duration: f64) {
+    if effective_seek(cli, assets) == SeekAccuracy::Fast {
+        cmd.arg("-avoid_negative_ts").arg("make_zero");
+    } else {
+        cmd.arg("-ss").arg(format!("{}", start_secs));
     }
+    cmd.arg("-t").arg(format!("{}", duration));
+}
 
-    println!("Created: {}", output_path.display());
+/// Soft subtitle tracks are an unseeked input, so they need the exact output trim. Pre-pass
+/// results that are read frame by frame (vidstabdetect's transforms) were computed on the
+/// whole unseeked decode, so the encode has to see the same frames
+fn effective_seek(cli: &Cli, assets: &FilterAssets) -> SeekAccuracy {
+    if assets.stabilize_transforms.is_some() {
+        return SeekAccuracy::Accurate;
+    }
+    match cli.seek_accuracy {
+        SeekAccuracy::Fast if assets.soft_subs.is_some() => SeekAccuracy::Hybrid,
+        mode => mode,
+    }
+}
 
-    Ok(())
+/// Add --intro/--outro images as looping inputs after the main video, in that order
+fn add_card_inputs(cmd: &mut Command, cli: &Cli) {
+    for card in cli.intro.iter().chain(&cli.outro) {
+        cmd.arg("-loop")
+            .arg("1")
+            .arg("-framerate")
+            .arg(format!("{}", cli.fps))
+            .arg("-t")
+            .arg(format!("{}", card.seconds))
+            .arg("-i")
+            .arg(&card.image);
+    }
 }
 
-fn get_video_title(yt_dlp: &Path, url: &str) -> Result<String> {
-    let output = Command::new(yt_dlp)
-        .arg("--get-title")
-        .arg("--no-playlist")
-        .arg(url)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::null())
-        .output()
-        .context("Failed to get video title")?;
+/// Turn a failed encoder exit into an error, with advice when it looks like the system
+/// killed ffmpeg for using too much memory
+fn check_encode_status(status: std::process::ExitStatus, format: &str) -> Result<()> {
+    if status.success() {
+        return Ok(());
+    }
 
-    if !output.status.success() {
-        bail!("Failed to fetch video title");
+    #[cfg(unix)]
+    let killed = {
+        use std::os::unix::process::ExitStatusExt;
+        // SIGKILL directly, or 128 + 9 when reported through a wrapper shell
+        status.signal() == Some(9) || status.code() == Some(137)
+    };
+    #[cfg(not(unix))]
+    let killed = false;
+
+    if killed {
+        bail!(
+            "ffmpeg was killed while creating the {}, most likely for running out of memory. \
+             Try a smaller --width, lower --fps, fewer --colors, or a shorter clip",
+            format
+        );
     }
+    bail!("ffmpeg failed to create {}", format)
+}
 
-    let title = String::from_utf8_lossy(&output.stdout).trim().to_string();
-    Ok(title)
+/// An encoder's ffmpeg invocation, built up in the order ffmpeg expects: inputs (with the
+/// seek), the output trim, the filter graph, codec options, then the output file
+struct FfmpegCommand<'a> {
+    cmd: Command,
+    cli: &'a Cli,
+    assets: &'a FilterAssets,
 }
 
-fn sanitize_filename(name: &str) -> String {
-    let re = Regex::new(r#"[<>:"/\\|?*]"#).unwrap();
-    let sanitized = re.replace_all(name, "_");
-    sanitized.chars().take(50).collect()
+impl<'a> FfmpegCommand<'a> {
+    /// The main video plus any card and soft subtitle inputs, trimmed to `duration`
+    /// seconds from `start_secs`
+    fn new(
+        ffmpeg: &Path,
+        video_path: &Path,
+        cli: &'a Cli,
+        assets: &'a FilterAssets,
+        start_secs: f64,
+        duration: f64,
+    ) -> Self {
+        let mut cmd = Command::new(ffmpeg);
+        // -y first, so a trailing option given without its value can't swallow it
+        cmd.arg("-y").args(&cli.ffmpeg_arg);
+        add_input_seek(&mut cmd, cli, assets);
+        if assets.rotation.is_some() {
+            cmd.arg("-noautorotate");
+        }
+        cmd.arg("-i").arg(video_path);
+        add_card_inputs(&mut cmd, cli);
+        if let Some(ref subs) = assets.soft_subs {
+            cmd.arg("-i").arg(subs);
+        }
+        add_output_seek(&mut cmd, cli, assets, start_secs, duration);
+
+        FfmpegCommand { cmd, cli, assets }
+    }
+
+    /// The video filter graph, labeled for explicit mapping when muxing soft subs
+    fn filter_graph(mut self, graph: &str) -> Self {
+        self.cmd.arg("-filter_complex");
+        if self.assets.soft_subs.is_some() {
+            self.cmd.arg(format!("{}[vout]", graph));
+        } else {
+            self.cmd.arg(graph);
+        }
+        self
+    }
+
+    fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<std::ffi::OsStr>,
+    {
+        self.cmd.args(args);
+        self
+    }
+
+    /// Soft subtitle track and audio options for video containers
+    fn streams(mut self, sub_codec: &str, audio_codec: &str) -> Self {
+        add_soft_sub_args(&mut self.cmd, self.cli, self.assets, sub_codec);
+        add_audio_args(&mut self.cmd, self.cli, self.assets, audio_codec);
+        self
+    }
+
+    /// The finished command line, writing `output_path`
+    fn command(mut self, output_path: &Path) -> Command {
+        self.cmd.arg(output_path);
+        self.cmd
+    }
+
+    fn status(self, output_path: &Path) -> Result<std::process::ExitStatus> {
+        self.command(output_path).status().context("Failed to run ffmpeg")
+    }
+
+    /// Write `output_path`, naming `format` in the error if ffmpeg fails
+    fn run(self, output_path: &Path, format: &str) -> Result<()> {
+        let status = self.status(output_path)?;
+        check_encode_status(status, format)
+    }
 }
 
-fn format_timestamp(secs: f64) -> String {
-    let mins = (secs / 60.0).floor() as u32;
-    let secs = (secs % 60.0).floor() as u32;
-    format!("{}m{}s", mins, secs)
+/// Palette and scaling settings behind --gif-profile
+struct GifTuning {
+    /// Palette size unless --colors is given (None derives it from --quality)
+    colors: Option<u32>,
+    /// palettegen stats_mode: build the palette from whole frames or just what changed
+    stats_mode: Option<&'static str>,
+    /// paletteuse dither algorithm and options
+    dither: &'static str,
+    /// paletteuse diff_mode: only re-dither the changed rectangle, so static areas
+    /// stay identical between frames and compress well
+    diff_mode: Option<&'static str>,
+    /// Scaler unless --scale-flags is given
+    scale_flags: &'static str,
 }
 
-fn build_subtitle_filter(sub_path: &Option<PathBuf>, custom_text: &Option<String>) -> Option<String> {
-    // Custom text takes priority over subtitle file
-    if let Some(text) = custom_text {
-        let text_escaped = text
-            .replace('\\', "\\\\")
-            .replace(':', "\\:")
-            .replace("'", "\\'");
-        // drawtext filter with bottom-center positioning, white text with black outline
-        return Some(format!(
-            "drawtext=text='{}':fontsize=24:fontcolor=white:borderw=2:bordercolor=black:x=(w-text_w)/2:y=h-th-20",
-            text_escaped
-        ));
+impl GifTuning {
+    fn for_profile(profile: Option<GifProfile>) -> Self {
+        match profile {
+            None => GifTuning {
+                colors: None,
+                stats_mode: None,
+                dither: "bayer",
+                diff_mode: None,
+                scale_flags: "lanczos",
+            },
+            Some(GifProfile::Crisp) => GifTuning {
+                colors: Some(256),
+                stats_mode: Some("full"),
+                dither: "none",
+                diff_mode: Some("rectangle"),
+                scale_flags: "lanczos",
+            },
+            Some(GifProfile::Smooth) => GifTuning {
+                colors: Some(256),
+                stats_mode: Some("full"),
+                dither: "sierra2_4a",
+                diff_mode: None,
+                scale_flags: "lanczos",
+            },
+            Some(GifProfile::Tiny) => GifTuning {
+                colors: Some(64),
+                stats_mode: Some("diff"),
+                dither: "bayer:bayer_scale=4",
+                diff_mode: Some("rectangle"),
+                scale_flags: "bilinear",
+            },
+        }
     }
+}
 
-    sub_path.as_ref().map(|subs| {
-        let sub_escaped = subs
-            .to_string_lossy()
-            .replace('\\', "\\\\")
-            .replace(':', "\\:")
-            .replace("'", "\\'");
-        format!("subtitles='{}'", sub_escaped)
-    })
+/// The scaled video chain, palettegen and paletteuse filters for a GIF encode
+fn gif_filters(cli: &Cli, assets: &FilterAssets) -> (String, String, String) {
+    let tuning = GifTuning::for_profile(cli.gif_profile);
+    let max_colors = cli
+        .colors
+        .or(tuning.colors)
+        .unwrap_or_else(|| 16 + ((cli.quality as f32 / 100.0) * 240.0) as u32);
+
+    let filter_base = build_video_filter(cli, assets, OutputFormat::Gif, tuning.scale_flags);
+    let transparent = cli.chroma_key.is_some();
+    let palettegen = format!(
+        "palettegen=max_colors={}{}{}",
+        max_colors,
+        tuning.stats_mode.map(|m| format!(":stats_mode={}", m)).unwrap_or_default(),
+        // GIF transparency is 1-bit: reserve a palette slot and cut alpha at 50%
+        if transparent { ":reserve_transparent=1" } else { "" }
+    );
+    let paletteuse = format!(
+        "paletteuse=dither={}{}{}",
+        tuning.dither,
+        tuning.diff_mode.map(|m| format!(":diff_mode={}", m)).unwrap_or_default(),
+        if transparent { ":alpha_threshold=128" } else { "" }
+    );
+    (filter_base, palettegen, paletteuse)
+}
+
+/// The GIF encode that builds its palette in the same graph
+fn gif_command<'a>(
+    ffmpeg: &Path,
+    video_path: &Path,
+    assets: &'a FilterAssets,
+    cli: &'a Cli,
+    start_secs: f64,
+    duration: f64,
+) -> FfmpegCommand<'a> {
+    let (filter_base, palettegen, paletteuse) = gif_filters(cli, assets);
+    // split holds every frame until palettegen has seen the last one
+    let filter_complex = format!("{},split[s0][s1];[s0]{}[p];[s1][p]{}", filter_base, palettegen, paletteuse);
+    FfmpegCommand::new(ffmpeg, video_path, cli, assets, start_secs, duration).filter_graph(&filter_complex)
 }
 
 fn encode_gif(
     ffmpeg: &Path,
     video_path: &Path,
     output_path: &Path,
-    sub_path: &Option<PathBuf>,
+    assets: &FilterAssets,
     cli: &Cli,
     start_secs: f64,
     duration: f64,
 ) -> Result<()> {
-    let mut filters = vec![
-        format!("fps={}", cli.fps),
-        format!("scale={}:-1:flags=lanczos", cli.width),
-    ];
-
-    if let Some(sub_filter) = build_subtitle_filter(sub_path, &cli.text) {
-        filters.insert(0, sub_filter);
+    if !cli.single_pass_palette {
+        return gif_command(ffmpeg, video_path, assets, cli, start_secs, duration).run(output_path, "GIF");
     }
 
-    let max_colors = 16 + ((cli.quality as f32 / 100.0) * 240.0) as u32;
+    let (filter_base, palettegen, paletteuse) = gif_filters(cli, assets);
+    // Palette from a sample of the frames, then a streaming encode that loads it in the graph
+    let palette_dir = TempDir::new().context("Failed to create temp directory")?;
+    let palette = palette_dir.path().join("palette.png");
+    FfmpegCommand::new(ffmpeg, video_path, cli, assets, start_secs, duration)
+        .filter_graph(&format!("{},fps={},{}", filter_base, PALETTE_SAMPLE_FPS, palettegen))
+        .args(["-frames:v", "1"])
+        .run(&palette, "GIF palette")?;
 
-    let filter_base = filters.join(",");
     let filter_complex = format!(
-        "{},split[s0][s1];[s0]palettegen=max_colors={}[p];[s1][p]paletteuse=dither=bayer",
-        filter_base, max_colors
+        "{}[v];movie='{}'[p];[v][p]{}",
+        filter_base,
+        escape_filter_path(&palette),
+        paletteuse
     );
+    FfmpegCommand::new(ffmpeg, video_path, cli, assets, start_secs, duration)
+        .filter_graph(&filter_complex)
+        .run(output_path, "GIF")
+}
 
-    let status = Command::new(ffmpeg)
-        .arg("-y")
-        .arg("-i")
-        .arg(video_path)
+/// Frames per second sampled to build the palette for --single-pass-palette
+const PALETTE_SAMPLE_FPS: u32 = 2;
+
+fn encode_webm(
+    ffmpeg: &Path,
+    video_path: &Path,
+    output_path: &Path,
+    assets: &FilterAssets,
+    cli: &Cli,
+    start_secs: f64,
+    duration: f64,
+) -> Result<()> {
+    let (codec, encoder, _) = cli.webm_codec.encoders();
+    if !ffmpeg_lists(ffmpeg, "-encoders", encoder) {
+        return Err(anyhow::anyhow!(
+            "This ffmpeg has no {} encoder for {} WebM; try another --webm-codec or an ffmpeg built with libvpx",
+            encoder,
+            codec
+        ))
+        .failure(Failure::ToolMissing);
+    }
+
+    webm_command(ffmpeg, video_path, assets, cli, start_secs, duration).run(output_path, "WebM")
+}
+
+fn webm_command<'a>(
+    ffmpeg: &Path,
+    video_path: &Path,
+    assets: &'a FilterAssets,
+    cli: &'a Cli,
+    start_secs: f64,
+    duration: f64,
+) -> FfmpegCommand<'a> {
+    let (_, encoder, audio_codec) = cli.webm_codec.encoders();
+    let filter_str = build_video_filter(cli, assets, OutputFormat::Webm, VIDEO_SCALE_FLAGS);
+    // VP9's CRF goes down to 10 here; VP8's usable range is 4-63, and it only holds a CRF
+    // under a bitrate ceiling (-b:v 0 means unconstrained only for VP9)
+    let (crf, bitrate) = match cli.webm_codec {
+        WebmCodec::Vp9 => (63 - ((cli.quality as f32 / 100.0) * 53.0) as u32, "0"),
+        WebmCodec::Vp8 => (63 - ((cli.quality as f32 / 100.0) * 59.0) as u32, "8M"),
+    };
+
+    // Keep the alpha channel from the corner mask or chroma key unless told otherwise
+    let alpha = cli.rounded_corners.is_some() || cli.chroma_key.is_some();
+    let pix_fmt = if alpha && cli.pix_fmt == DEFAULT_PIX_FMT {
+        "yuva420p"
+    } else {
+        cli.pix_fmt.as_str()
+    };
+
+    FfmpegCommand::new(ffmpeg, video_path, cli, assets, start_secs, duration)
+        .filter_graph(&filter_str)
+        .args(["-c:v", encoder, "-crf", &crf.to_string(), "-b:v", bitrate])
+        .streams("webvtt", audio_codec)
+        .args(["-pix_fmt", pix_fmt])
+        // libvpx refuses to encode VP8 transparency with alt-ref frames on
+        .args(if alpha && cli.webm_codec == WebmCodec::Vp8 {
+            &["-auto-alt-ref", "0"][..]
+        } else {
+            &[]
+        })
+}
+
+/// Why the clip can't be stream copied into the output container, if anything
+fn stream_copy_blocker(
+    media: &MediaInfo,
+    assets: &FilterAssets,
+    cli: &Cli,
+    format: OutputFormat,
+) -> Option<String> {
+    let (video_codecs, audio_codecs): (&[&str], &[&str]) = match format {
+        OutputFormat::Mp4 => (&["h264", "hevc", "av1"], &["aac", "mp3", "opus"]),
+        OutputFormat::Webm => (&["vp8", "vp9", "av1"], &["opus", "vorbis"]),
+        _ => return Some(format!("{:?} output is always re-encoded", format)),
+    };
+
+    let filters = [
+        (assets.sub_path.is_some() || cli.text.is_some(), "subtitles are burned in; add --no-subs"),
+        (cli.rotate.is_some(), "--rotate"),
+        (assets.tonemap, "--tonemap"),
+        (assets.crop.is_some(), "cropping"),
+        (assets.soft_subs.is_some(), "--soft-subs"),
+        (assets.stabilize_transforms.is_some(), "--stabilize"),
+        (!assets.supercut_segments.is_empty(), "supercut"),
+        (assets.speed_ramp_filter.is_some(), "speed change"),
+        (assets.cards.is_some(), "--intro/--outro"),
+        (assets.edge_pad.is_some(), "--pad-black/--pad-hold"),
+        (cli.chroma_key.is_some(), "--chroma-key"),
+        (cli.pad_to.is_some() || cli.blur_pad.is_some(), "padding"),
+        (cli.rounded_corners.is_some(), "--rounded-corners"),
+        (cli.burn_timestamp, "--burn-timestamp"),
+        (cli.vf_prepend.is_some() || cli.vf_append.is_some(), "--vf-prepend/--vf-append"),
+    ];
+    if let Some((_, reason)) = filters.iter().find(|(active, _)| *active) {
+        return Some(reason.to_string());
+    }
+
+    match stream_codec(media, "video") {
+        Some(codec) if video_codecs.contains(&codec.as_str()) => {}
+        Some(codec) => return Some(format!("source video is {}", codec)),
+        None => return Some("couldn't read the source video codec".to_string()),
+    }
+
+    if cli.audio
+        && let Some(codec) = stream_codec(media, "audio")
+        && !audio_codecs.contains(&codec.as_str())
+    {
+        return Some(format!("source audio is {}", codec));
+    }
+
+    None
+}
+
+/// How far back from the start to look for a keyframe before scanning from the beginning
+const KEYFRAME_SEARCH_WINDOW: f64 = 30.0;
+
+/// Time of the last video keyframe at or before `at`, read with ffprobe
+fn preceding_keyframe(config: &config::Config, video_path: &Path, at: f64) -> Option<f64> {
+    let ffprobe = config.ffprobe_path().ok()?;
+    let search = |from: f64| -> Option<f64> {
+        let output = Command::new(&ffprobe)
+            .arg("-v")
+            .arg("error")
+            .arg("-select_streams")
+            .arg("v:0")
+            .arg("-skip_frame")
+            .arg("nokey")
+            .arg("-read_intervals")
+            .arg(format!("{}%{}", from, at + 0.001))
+            .arg("-show_entries")
+            .arg("frame=pts_time")
+            .arg("-of")
+            .arg("csv=p=0")
+            .arg(video_path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.trim().trim_end_matches(',').parse::<f64>().ok())
+            .filter(|&time| time <= at + 0.001)
+            .max_by(f64::total_cmp)
+    };
+
+    let from = (at - KEYFRAME_SEARCH_WINDOW).max(0.0);
+    search(from).or_else(|| if from > 0.0 { search(0.0) } else { None })
+}
+
+/// Cut the clip out with `-c copy`: near-instant and lossless, but the start snaps back to
+/// the previous keyframe
+fn copy_streams(
+    ffmpeg: &Path,
+    video_path: &Path,
+    output_path: &Path,
+    cli: &Cli,
+    audio_track: Option<usize>,
+    start_secs: f64,
+    duration: f64,
+) -> Result<()> {
+    let mut cmd = Command::new(ffmpeg);
+    cmd.arg("-y")
+        .args(&cli.ffmpeg_arg)
         .arg("-ss")
         .arg(format!("{}", start_secs))
+        .arg("-i")
+        .arg(video_path)
         .arg("-t")
         .arg(format!("{}", duration))
-        .arg("-vf")
-        .arg(&filter_complex)
+        .arg("-map")
+        .arg("0:v:0");
+
+    if cli.audio {
+        cmd.arg("-map").arg(audio_map(audio_track));
+    }
+
+    let status = cmd
+        .arg("-c")
+        .arg("copy")
+        .arg("-avoid_negative_ts")
+        .arg("make_zero")
         .arg(output_path)
         .status()
         .context("Failed to run ffmpeg")?;
 
-    if !status.success() {
-        bail!("ffmpeg failed to create GIF");
+    if !status.success() {
+        bail!("ffmpeg failed to copy the clip");
+    }
+
+    Ok(())
+}
+
+/// Encode only the clip's audio, picking the codec from the output extension
+fn extract_audio(
+    ffmpeg: &Path,
+    video_path: &Path,
+    output_path: &Path,
+    assets: &FilterAssets,
+    cli: &Cli,
+    start_secs: f64,
+    duration: f64,
+) -> Result<()> {
+    let status = audio_command(ffmpeg, video_path, output_path, assets, cli, start_secs, duration)?
+        .status(output_path)?;
+
+    if !status.success() {
+        bail!("ffmpeg failed to extract audio (does the video have sound?)");
+    }
+
+    Ok(())
+}
+
+/// The audio-only encode, with the codec picked from `output_path`'s extension
+fn audio_command<'a>(
+    ffmpeg: &Path,
+    video_path: &Path,
+    output_path: &Path,
+    assets: &'a FilterAssets,
+    cli: &'a Cli,
+    start_secs: f64,
+    duration: f64,
+) -> Result<FfmpegCommand<'a>> {
+    let ext = output_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    let codec_args: &[&str] = match ext.as_str() {
+        "mp3" => &["-c:a", "libmp3lame", "-q:a", "2"],
+        "m4a" | "aac" => &["-c:a", "aac", "-b:a", "192k"],
+        "opus" => &["-c:a", "libopus", "-b:a", "128k"],
+        "ogg" => &["-c:a", "libvorbis", "-q:a", "5"],
+        "flac" => &["-c:a", "flac"],
+        "wav" => &["-c:a", "pcm_s16le"],
+        _ => bail!("Unsupported audio extension \"{}\" (use mp3, m4a, opus, ogg, flac or wav)", ext),
+    };
+
+    let mut cmd = FfmpegCommand::new(ffmpeg, video_path, cli, assets, start_secs, duration).args(["-vn"]);
+    if let Some(track) = assets.audio_track {
+        cmd = cmd.args(["-map".to_string(), audio_map(Some(track))]);
+    }
+    if let Some(ref filter) = assets.audio_filter {
+        cmd = cmd.args(["-af", filter.as_str()]);
+    }
+    Ok(cmd.args(codec_args))
+}
+
+/// Map the labeled video, optional audio and the subtitle input (always input 1, since
+/// soft subs never combine with cards) when muxing --soft-subs as a `codec` track
+fn add_soft_sub_args(cmd: &mut Command, cli: &Cli, assets: &FilterAssets, codec: &str) {
+    if assets.soft_subs.is_none() {
+        return;
+    }
+
+    cmd.arg("-map").arg("[vout]");
+    if cli.audio {
+        cmd.arg("-map").arg(audio_map(assets.audio_track));
+    }
+    cmd.arg("-map")
+        .arg("1:s:0")
+        .arg("-c:s")
+        .arg(codec)
+        .arg("-metadata:s:s:0")
+        .arg(format!("language={}", cli.lang));
+}
+
+/// Audio options for video outputs: dropped unless --audio, then encoded with `codec`
+/// (through the --speed audio filter when there is one)
+fn add_audio_args(cmd: &mut Command, cli: &Cli, assets: &FilterAssets, codec: &str) {
+    if !cli.audio {
+        cmd.arg("-an");
+        return;
+    }
+
+    // Soft subs map every stream explicitly, audio included
+    if let Some(track) = assets.audio_track
+        && assets.soft_subs.is_none()
+    {
+        cmd.arg("-map").arg(audio_map(Some(track)));
+    }
+    if let Some(ref filter) = assets.audio_filter {
+        cmd.arg("-af").arg(filter);
+    }
+    cmd.arg("-c:a").arg(codec).arg("-b:a").arg("128k");
+}
+
+/// `-map` target for the chosen audio track, or the first one if the video has any
+fn audio_map(track: Option<usize>) -> String {
+    match track {
+        Some(track) => format!("0:a:{}", track),
+        None => "0:a:0?".to_string(),
+    }
+}
+
+fn encode_webp(
+    ffmpeg: &Path,
+    video_path: &Path,
+    output_path: &Path,
+    assets: &FilterAssets,
+    cli: &Cli,
+    start_secs: f64,
+    duration: f64,
+) -> Result<()> {
+    webp_command(ffmpeg, video_path, assets, cli, start_secs, duration).run(output_path, "WebP")
+}
+
+fn webp_command<'a>(
+    ffmpeg: &Path,
+    video_path: &Path,
+    assets: &'a FilterAssets,
+    cli: &'a Cli,
+    start_secs: f64,
+    duration: f64,
+) -> FfmpegCommand<'a> {
+    let filter_str = build_video_filter(cli, assets, OutputFormat::Webp, VIDEO_SCALE_FLAGS);
+    let mut cmd = FfmpegCommand::new(ffmpeg, video_path, cli, assets, start_secs, duration)
+        .filter_graph(&filter_str)
+        .args(["-c:v", "libwebp", "-loop", "0"])
+        .args(["-compression_level", &cli.compression_level.to_string(), "-an"]);
+
+    cmd = if cli.lossless {
+        cmd.args(["-lossless", "1"])
+    } else {
+        cmd.args(["-quality", &cli.quality.min(100).to_string()])
+    };
+
+    if cli.rounded_corners.is_some() || cli.chroma_key.is_some() {
+        // Keep the alpha channel from the corner mask or chroma key
+        cmd = cmd.args(["-pix_fmt", "yuva420p"]);
     }
-
-    Ok(())
+    cmd
 }
 
-fn encode_webm(
+fn encode_mp4(
     ffmpeg: &Path,
     video_path: &Path,
     output_path: &Path,
-    sub_path: &Option<PathBuf>,
+    assets: &FilterAssets,
     cli: &Cli,
     start_secs: f64,
     duration: f64,
 ) -> Result<()> {
-    let mut filters = vec![
-        format!("fps={}", cli.fps),
-        format!("scale={}:-1", cli.width),
-    ];
+    mp4_command(ffmpeg, video_path, assets, cli, start_secs, duration).run(output_path, "MP4")
+}
+
+fn mp4_command<'a>(
+    ffmpeg: &Path,
+    video_path: &Path,
+    assets: &'a FilterAssets,
+    cli: &'a Cli,
+    start_secs: f64,
+    duration: f64,
+) -> FfmpegCommand<'a> {
+    let mut filter_str = build_video_filter(cli, assets, OutputFormat::Mp4, VIDEO_SCALE_FLAGS);
+    let crf = 51 - ((cli.quality as f32 / 100.0) * 41.0) as u32;
+
+    if cli.compat {
+        // Level 3.0 allows 1620 macroblocks per frame (720x576); shrink anything larger,
+        // keeping the aspect, and round both sides down to even numbers for 4:2:0
+        filter_str.push_str(&format!(
+            ",scale=w='trunc(iw*min(1,sqrt({}/(iw*ih)))/2)*2':h=-2",
+            COMPAT_MAX_PIXELS
+        ));
+    }
 
-    if let Some(sub_filter) = build_subtitle_filter(sub_path, &cli.text) {
-        filters.insert(0, sub_filter);
+    let mut cmd = FfmpegCommand::new(ffmpeg, video_path, cli, assets, start_secs, duration)
+        .filter_graph(&filter_str)
+        .args(["-c:v", "libx264", "-crf", &crf.to_string(), "-preset", "medium"]);
+    if cli.compat {
+        cmd = cmd.args(["-profile:v", "baseline", "-level", "3.0"]);
     }
 
-    let filter_str = filters.join(",");
-    let crf = 63 - ((cli.quality as f32 / 100.0) * 53.0) as u32;
+    let pix_fmt = if cli.compat { "yuv420p" } else { cli.pix_fmt.as_str() };
+    cmd.streams("mov_text", "aac")
+        .args(["-pix_fmt", pix_fmt, "-movflags", "+faststart"])
+}
 
+/// Grab the frame at `at` seconds from the finished MP4, so the poster shows the clip as
+/// encoded (subtitles, crop and all), and mux it back in as the cover image
+fn attach_poster(ffmpeg: &Path, output_path: &Path, temp_path: &Path, at: f64) -> Result<()> {
+    let poster = temp_path.join("poster.jpg");
     let status = Command::new(ffmpeg)
         .arg("-y")
-        .arg("-i")
-        .arg(video_path)
+        .arg("-v")
+        .arg("error")
         .arg("-ss")
-        .arg(format!("{}", start_secs))
-        .arg("-t")
-        .arg(format!("{}", duration))
-        .arg("-vf")
-        .arg(&filter_str)
-        .arg("-c:v")
-        .arg("libvpx-vp9")
-        .arg("-crf")
-        .arg(format!("{}", crf))
-        .arg("-b:v")
-        .arg("0")
-        .arg("-an")
+        .arg(format!("{}", at))
+        .arg("-i")
         .arg(output_path)
+        .arg("-frames:v")
+        .arg("1")
+        .arg("-q:v")
+        .arg("2")
+        .arg(&poster)
         .status()
         .context("Failed to run ffmpeg")?;
-
     if !status.success() {
-        bail!("ffmpeg failed to create WebM");
-    }
-
-    Ok(())
-}
-
-fn encode_mp4(
-    ffmpeg: &Path,
-    video_path: &Path,
-    output_path: &Path,
-    sub_path: &Option<PathBuf>,
-    cli: &Cli,
-    start_secs: f64,
-    duration: f64,
-) -> Result<()> {
-    let mut filters = vec![
-        format!("fps={}", cli.fps),
-        format!("scale={}:-1", cli.width),
-    ];
-
-    if let Some(sub_filter) = build_subtitle_filter(sub_path, &cli.text) {
-        filters.insert(0, sub_filter);
+        bail!("ffmpeg failed to extract the poster frame");
     }
 
-    let filter_str = filters.join(",");
-    let crf = 51 - ((cli.quality as f32 / 100.0) * 41.0) as u32;
-
+    let with_poster = temp_path.join("with_poster.mp4");
     let status = Command::new(ffmpeg)
         .arg("-y")
+        .arg("-v")
+        .arg("error")
         .arg("-i")
-        .arg(video_path)
-        .arg("-ss")
-        .arg(format!("{}", start_secs))
-        .arg("-t")
-        .arg(format!("{}", duration))
-        .arg("-vf")
-        .arg(&filter_str)
-        .arg("-c:v")
-        .arg("libx264")
-        .arg("-crf")
-        .arg(format!("{}", crf))
-        .arg("-preset")
-        .arg("medium")
-        .arg("-an")
+        .arg(output_path)
+        .arg("-i")
+        .arg(&poster)
+        .arg("-map")
+        .arg("0")
+        .arg("-map")
+        .arg("1")
+        .arg("-c")
+        .arg("copy")
+        .arg("-disposition:v:1")
+        .arg("attached_pic")
         .arg("-movflags")
         .arg("+faststart")
-        .arg(output_path)
+        .arg(&with_poster)
         .status()
         .context("Failed to run ffmpeg")?;
-
     if !status.success() {
-        bail!("ffmpeg failed to create MP4");
+        bail!("ffmpeg failed to attach the poster");
     }
 
+    // The temp directory may be on another filesystem, so copy rather than rename
+    fs::copy(&with_poster, output_path)
+        .with_context(|| format!("Failed to write {}", output_path.display()))?;
+    println!("Attached poster frame from {:.1}s", at);
     Ok(())
 }
 
+/// Parse a timestamp into seconds.
+///
+/// Accepted forms:
+/// - `SS` or `SS.sss` - plain seconds, any magnitude (e.g. `90`)
+/// - `M:SS` - minutes and seconds; minutes may exceed 59 (e.g. `75:30`)
+/// - `H:MM:SS` - hours, minutes, seconds; minutes must be below 60
+///
+/// Seconds must be below 60 in both colon forms.
 fn parse_timestamp(ts: &str) -> Result<f64> {
     if let Ok(secs) = ts.parse::<f64>() {
         return Ok(secs);
@@ -592,36 +4471,181 @@ fn parse_timestamp(ts: &str) -> Result<f64> {
         let hours: f64 = caps.get(1).map_or(0.0, |m| m.as_str().parse().unwrap_or(0.0));
         let minutes: f64 = caps[2].parse().unwrap_or(0.0);
         let seconds: f64 = caps[3].parse().unwrap_or(0.0);
+
+        if seconds >= 60.0 {
+            bail!("Invalid timestamp {}: seconds must be below 60", ts);
+        }
+        if caps.get(1).is_some() && minutes >= 60.0 {
+            bail!("Invalid timestamp {}: minutes must be below 60 in HH:MM:SS", ts);
+        }
+
         return Ok(hours * 3600.0 + minutes * 60.0 + seconds);
     }
 
     bail!("Invalid timestamp format: {}. Use MM:SS, HH:MM:SS, or seconds", ts)
 }
 
-fn get_video_duration(config: &config::Config, video_path: &Path) -> Result<f64> {
-    // Try ffprobe first (preferred method for getting duration)
-    if let Ok(ffprobe) = config.ffprobe_path() {
-        if ffprobe.exists() {
-            let output = Command::new(&ffprobe)
-                .arg("-v")
-                .arg("error")
-                .arg("-show_entries")
-                .arg("format=duration")
-                .arg("-of")
-                .arg("default=noprint_wrappers=1:nokey=1")
-                .arg(video_path)
-                .stdout(Stdio::piped())
-                .stderr(Stdio::null())
-                .output()
-                .context("Failed to run ffprobe")?;
-
-            if output.status.success() {
-                let duration_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                if let Ok(duration) = duration_str.parse::<f64>() {
-                    return Ok(duration);
-                }
-            }
+/// Get the source frame rate and total frame count via ffprobe
+fn get_video_frame_info(media: &MediaInfo) -> Result<(f64, u64)> {
+    let video = media.video().with_context(|| media.missing("ffprobe found no video stream"))?;
+    let fps = video.frame_rate.context("Could not determine the video frame rate")?;
+
+    // Some containers (e.g. mkv) don't store a frame count; estimate from duration
+    let frame_count = match (video.frame_count, media.duration) {
+        (Some(n), _) => n,
+        (None, Some(duration)) => (duration * fps).round() as u64,
+        (None, None) => bail!("Could not determine the video's frame count"),
+    };
+
+    Ok((fps, frame_count))
+}
+
+fn get_video_dimensions(media: &MediaInfo) -> Result<(u32, u32)> {
+    match media.video().and_then(|v| v.width.zip(v.height)) {
+        Some(size) => Ok(size),
+        None => bail!(media.missing("ffprobe failed to read the video dimensions")),
+    }
+}
+
+/// Clockwise rotation (0, 90, 180 or 270) a player applies to the first video stream, from
+/// its display matrix or the older `rotate` tag
+fn video_rotation(media: &MediaInfo) -> u32 {
+    let degrees = media.video().and_then(|v| v.rotation).unwrap_or(0.0);
+    let quarter_turns = (degrees / 90.0).round() as i64;
+    (quarter_turns.rem_euclid(4) * 90) as u32
+}
+
+/// Filters that turn frames clockwise by `degrees`
+fn rotation_filter(degrees: u32) -> Option<&'static str> {
+    match degrees {
+        90 => Some("transpose=clock"),
+        180 => Some("hflip,vflip"),
+        270 => Some("transpose=cclock"),
+        _ => None,
+    }
+}
+
+/// Prefix a `-vf` chain with the rotation, for passes run with autorotate off
+fn oriented_filter(rotation: Option<u32>, filter: &str) -> String {
+    match rotation.and_then(rotation_filter) {
+        Some(rotate) => format!("{},{}", rotate, filter),
+        None => filter.to_string(),
+    }
+}
+
+/// Frame size after turning by `rotation`
+fn oriented_dimensions((width, height): (u32, u32), rotation: Option<u32>) -> (u32, u32) {
+    match rotation {
+        Some(90 | 270) => (height, width),
+        _ => (width, height),
+    }
+}
+
+/// Whether the source uses an HDR transfer function (PQ or HLG)
+fn is_hdr(media: &MediaInfo) -> bool {
+    media
+        .video()
+        .and_then(|v| v.color_transfer.as_deref())
+        .is_some_and(|transfer| matches!(transfer, "smpte2084" | "arib-std-b67"))
+}
+
+fn get_audio_sample_rate(media: &MediaInfo, track: usize) -> Result<u32> {
+    media
+        .streams_of("audio")
+        .nth(track)
+        .and_then(|a| a.sample_rate)
+        .context("Could not read the audio sample rate (does the video have sound?)")
+}
+
+/// Probe the source once for the rest of the run. A failed probe leaves every field unknown
+/// and is only reported by the uses that have no fallback.
+fn probe_source(config: &config::Config, video_path: &Path, cached: bool) -> MediaInfo {
+    MediaInfo::load(config, video_path, cached).unwrap_or_else(|e| MediaInfo {
+        error: Some(format!("{:#}", e)),
+        ..MediaInfo::default()
+    })
+}
+
+/// Language tag of each audio stream, in order ("" where untagged)
+fn audio_track_languages(media: &MediaInfo) -> Vec<String> {
+    media.streams_of("audio").map(|a| a.language.clone()).collect()
+}
+
+/// Two-letter codes and the three-letter ISO 639-2 tags containers usually carry
+const LANGUAGE_CODES: &[(&str, &[&str])] = &[
+    ("en", &["eng"]),
+    ("ja", &["jpn"]),
+    ("fr", &["fra", "fre"]),
+    ("de", &["deu", "ger"]),
+    ("es", &["spa"]),
+    ("it", &["ita"]),
+    ("pt", &["por"]),
+    ("ru", &["rus"]),
+    ("zh", &["zho", "chi"]),
+    ("ko", &["kor"]),
+    ("nl", &["nld", "dut"]),
+    ("sv", &["swe"]),
+    ("pl", &["pol"]),
+    ("tr", &["tur"]),
+    ("ar", &["ara"]),
+    ("hi", &["hin"]),
+];
+
+/// Whether a stream's language tag is `wanted`, given as either a two- or three-letter code
+fn language_matches(tag: &str, wanted: &str) -> bool {
+    let tag = tag.to_lowercase();
+    if tag.is_empty() {
+        return false;
+    }
+    tag == wanted
+        || LANGUAGE_CODES.iter().any(|(short, long)| {
+            (wanted == *short && long.contains(&tag.as_str())) || (tag == *short && long.contains(&wanted))
+        })
+}
+
+/// The audio stream (0-based among audio streams) picked by --audio-track or --audio-lang.
+/// None leaves the choice to ffmpeg, which takes the first audio track if there is one.
+fn select_audio_track(media: &MediaInfo, cli: &Cli) -> Result<Option<usize>> {
+    if cli.audio_track.is_none() && cli.audio_lang.is_none() {
+        return Ok(None);
+    }
+
+    let languages = audio_track_languages(media);
+    let listing = || {
+        languages
+            .iter()
+            .enumerate()
+            .map(|(i, lang)| format!("{} ({})", i + 1, if lang.is_empty() { "untagged" } else { lang }))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    if languages.is_empty() {
+        fail!(Failure::NotFound, "The video has no audio tracks");
+    }
+
+    if let Some(track) = cli.audio_track {
+        let index = track as usize - 1;
+        if index >= languages.len() {
+            fail!(Failure::NotFound, "No audio track {}; the video has: {}", track, listing());
+        }
+        return Ok(Some(index));
+    }
+
+    let wanted = cli.audio_lang.as_deref().unwrap_or_default().to_lowercase();
+    let index = languages.iter().position(|lang| language_matches(lang, &wanted));
+    match index {
+        Some(index) => {
+            println!("Audio: track {} ({})", index + 1, languages[index]);
+            Ok(Some(index))
         }
+        None => fail!(Failure::NotFound, "No \"{}\" audio track; the video has: {}", wanted, listing()),
+    }
+}
+
+fn get_video_duration(config: &config::Config, media: &MediaInfo, video_path: &Path) -> Result<f64> {
+    // The probe's format duration is preferred
+    if let Some(duration) = media.duration {
+        return Ok(duration);
     }
 
     // Fallback: use ffmpeg to parse duration from output
@@ -645,7 +4669,7 @@ fn get_video_duration(config: &config::Config, video_path: &Path) -> Result<f64>
         return Ok(hours * 3600.0 + minutes * 60.0 + seconds);
     }
 
-    bail!("Could not determine video duration")
+    bail!(media.missing("Could not determine video duration"))
 }
 
 fn find_subtitle_file(dir: &Path, lang: &str) -> Option<PathBuf> {
@@ -668,11 +4692,50 @@ fn is_url(s: &str) -> bool {
     s.starts_with("http://") || s.starts_with("https://")
 }
 
+fn is_hls_url(s: &str) -> bool {
+    is_url(s)
+        && s.split(['?', '#'])
+            .next()
+            .is_some_and(|path| path.to_lowercase().ends_with(".m3u8"))
+}
+
 fn is_youtube_url(s: &str) -> bool {
     s.contains("youtube.com") || s.contains("youtu.be")
 }
 
-fn download_file(url: &str, dest: &Path) -> Result<()> {
+/// The 11-character video ID from a YouTube watch, short, embed, or youtu.be URL
+fn youtube_video_id(url: &str) -> Option<String> {
+    let id_re = Regex::new(r"(?:[?&]v=|youtu\.be/|/shorts/|/embed/|/live/)([A-Za-z0-9_-]{11})").unwrap();
+    id_re.captures(url).map(|caps| caps[1].to_string())
+}
+
+/// Where subtitles for a YouTube video are cached, keyed by video ID and language.
+/// Manual-only (--no-auto-sub) and manual-first (--prefer-manual) fetches are cached
+/// separately since they may differ.
+fn subtitle_cache_path(url: &str, cli: &Cli) -> Option<PathBuf> {
+    let id = youtube_video_id(url)?;
+    let variant = if cli.no_auto_sub {
+        ".manual"
+    } else if cli.prefer_manual {
+        ".prefer-manual"
+    } else {
+        ""
+    };
+    let dir = config::Config::cache_dir().ok()?.join("subs");
+    Some(dir.join(format!("{}.{}{}.srt", id, cli.lang, variant)))
+}
+
+fn store_cached_subs(subs: &Path, cache: &Path) -> Result<()> {
+    if let Some(dir) = cache.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::copy(subs, cache)?;
+    Ok(())
+}
+
+/// Download `url` to `dest`, following redirects. Returns the server's name for the file:
+/// the Content-Disposition filename, or the last path segment of the URL redirected to.
+fn download_file(url: &str, dest: &Path) -> Result<Option<String>> {
     let response = reqwest::blocking::get(url)
         .with_context(|| format!("Failed to download {}", url))?;
 
@@ -680,29 +4743,196 @@ fn download_file(url: &str, dest: &Path) -> Result<()> {
         bail!("Failed to download {}: HTTP {}", url, response.status());
     }
 
+    let filename = response
+        .headers()
+        .get(reqwest::header::CONTENT_DISPOSITION)
+        .and_then(|value| content_disposition_filename(&String::from_utf8_lossy(value.as_bytes())))
+        .or_else(|| {
+            let final_url = response.url();
+            (final_url.as_str() != url)
+                .then(|| final_url.path_segments()?.next_back().map(percent_decode))
+                .flatten()
+                .filter(|name| !name.is_empty())
+        });
+
     let bytes = response.bytes()
         .with_context(|| format!("Failed to read response from {}", url))?;
 
     fs::write(dest, &bytes)
         .with_context(|| format!("Failed to write to {}", dest.display()))?;
 
-    Ok(())
+    Ok(filename)
 }
 
-fn extract_embedded_subs(ffmpeg: &Path, video_path: &Path, output_path: &Path) -> Result<bool> {
-    // Try to extract embedded subtitles using ffmpeg
-    let status = Command::new(ffmpeg)
+/// Filename from a Content-Disposition header, preferring the RFC 5987 `filename*=`
+/// form (`UTF-8''name%20here.mp4`) over plain `filename=`
+fn content_disposition_filename(header: &str) -> Option<String> {
+    let mut plain = None;
+    let mut extended = None;
+    for param in header.split(';').map(str::trim) {
+        let Some((key, value)) = param.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"');
+        match key.trim().to_ascii_lowercase().as_str() {
+            "filename*" => {
+                // charset'language'encoded-value
+                extended = value.rsplit('\'').next().map(percent_decode);
+            }
+            "filename" => plain = Some(percent_decode(value)),
+            _ => {}
+        }
+    }
+
+    // Keep only the final component; the header is untrusted
+    extended
+        .or(plain)
+        .and_then(|name| name.rsplit(['/', '\\']).next().map(str::to_string))
+        .filter(|name| !name.is_empty() && name != "." && name != "..")
+}
+
+/// Decode %XX escapes, replacing invalid UTF-8
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%')
+            .then(|| s.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Extra audio transcribed on each side of a --start/--end clip, so later adjustments
+/// (silence snapping, interactive trim) still have subtitles to show
+const TRANSCRIBE_MARGIN: f64 = 5.0;
+
+/// Source range worth transcribing: just around the clip in plain timestamp mode, otherwise
+/// everything, since dialogue search and chapters need the whole video's subtitles
+fn transcription_range(cli: &Cli) -> Result<(f64, Option<f64>)> {
+    let timestamp_mode =
+        cli.from.is_empty()
+            && cli.cue.is_none()
+            && cli.cue_range.is_none()
+            && cli.continue_after.is_none()
+            && cli.chapter.is_none()
+            && cli.clip.is_none()
+            && cli.frame_start.is_none()
+            && cli.at.is_none();
+    if !timestamp_mode {
+        return Ok((0.0, None));
+    }
+
+    let start = match &cli.start {
+        Some(start) => parse_timestamp(start)?,
+        None => 0.0,
+    };
+    let end = match (&cli.end, &cli.duration) {
+        (Some(end), _) => Some(parse_timestamp(end)?),
+        (None, Some(duration)) => Some(start + parse_timestamp(duration)?),
+        (None, None) => None,
+    };
+    Ok(((start - TRANSCRIBE_MARGIN).max(0.0), end.map(|end| end + TRANSCRIBE_MARGIN)))
+}
+
+/// Try OpenSubtitles for a file with no subtitles of its own. Lookup failures only warn,
+/// so the clip still gets made without subtitles.
+fn fetch_opensubtitles(
+    config: &config::Config,
+    video_path: &Path,
+    lang: &str,
+    temp_path: &Path,
+) -> Result<Option<PathBuf>> {
+    let Some(api_key) = config.opensubtitles_api_key.as_deref() else {
+        bail!(
+            "--fetch-subs needs an OpenSubtitles API key: set opensubtitles_api_key in {}",
+            config::Config::config_path()?.display()
+        );
+    };
+
+    println!("Searching OpenSubtitles...");
+    let dest = temp_path.join("opensubtitles.srt");
+    match opensubtitles::fetch_subtitles(api_key, video_path, lang, &dest) {
+        Ok(true) => {
+            println!("Downloaded subtitles from OpenSubtitles");
+            Ok(Some(dest))
+        }
+        Ok(false) => {
+            eprintln!("Warning: OpenSubtitles has no \"{}\" subtitles matching this file", lang);
+            Ok(None)
+        }
+        Err(e) => {
+            eprintln!("Warning: OpenSubtitles lookup failed: {:#}", e);
+            Ok(None)
+        }
+    }
+}
+
+/// Codec name of the first stream of a kind ("video", "audio", "subtitle")
+fn stream_codec(media: &MediaInfo, kind: &str) -> Option<String> {
+    media.streams_of(kind).next().map(|s| s.codec.clone()).filter(|codec| !codec.is_empty())
+}
+
+/// Image-based subtitle codecs, which ffmpeg can't convert to a text format
+const BITMAP_SUB_CODECS: &[&str] = &["hdmv_pgs_subtitle", "dvd_subtitle", "dvb_subtitle", "xsub"];
+
+/// Extract the first embedded subtitle stream into `temp_path`. None when the file has no
+/// subtitle stream; when it has one that can't be extracted, warns with ffmpeg's reason
+/// so the caller can fall back to other sources.
+fn extract_embedded_subs(
+    media: &MediaInfo,
+    ffmpeg: &Path,
+    video_path: &Path,
+    temp_path: &Path,
+) -> Result<Option<PathBuf>> {
+    let Some(codec) = stream_codec(media, "subtitle") else {
+        return Ok(None);
+    };
+    if BITMAP_SUB_CODECS.contains(&codec.as_str()) {
+        eprintln!(
+            "Warning: The embedded subtitles are images ({}), which can't be burned as text; skipping them",
+            codec
+        );
+        return Ok(None);
+    }
+
+    let output_path = match codec.as_str() {
+        "ass" | "ssa" => temp_path.join("extracted.ass"),
+        _ => temp_path.join("extracted.srt"),
+    };
+    let output = Command::new(ffmpeg)
         .arg("-y")
+        .arg("-v")
+        .arg("error")
         .arg("-i")
         .arg(video_path)
         .arg("-map")
         .arg("0:s:0")  // First subtitle stream
-        .arg(output_path)
-        .stderr(Stdio::null())
-        .status()
+        .arg(&output_path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
         .context("Failed to run ffmpeg for subtitle extraction")?;
 
-    Ok(status.success())
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("no error output");
+        eprintln!("Warning: Couldn't extract the embedded {} subtitles: {}", codec, reason.trim());
+        return Ok(None);
+    }
+    Ok(Some(output_path))
 }
 
 fn get_filename_from_path(path: &str) -> String {
@@ -716,7 +4946,7 @@ fn get_filename_from_path(path: &str) -> String {
 fn get_filename_from_url(url: &str) -> String {
     // Try to extract filename from URL path
     url.split('/')
-        .last()
+        .next_back()
         .and_then(|s| s.split('?').next())
         .map(|s| {
             Path::new(s)
@@ -728,31 +4958,148 @@ fn get_filename_from_url(url: &str) -> String {
         .unwrap_or_else(|| "video".to_string())
 }
 
+/// `gifclip match`: load the input's subtitles and list what each `find_dialogue`
+/// strategy matches, marking the line dialogue mode would pick
+fn run_match(
+    config: &config::Config,
+    input: &str,
+    query: &str,
+    subs: Option<&str>,
+    lang: &str,
+    exact_diacritics: bool,
+) -> Result<()> {
+    let temp_dir = TempDir::new().context("Failed to create temp directory")?;
+    let temp_path = temp_dir.path();
+    let ffmpeg = config.ffmpeg_path()?;
+
+    let sub_path = if let Some(subs_input) = subs {
+        Some(resolve_subs_input(subs_input, temp_path)?)
+    } else if is_url(input) && is_youtube_url(input) {
+        println!("Downloading subtitles...");
+        let status = Command::new(config.yt_dlp_path()?)
+            .arg("--skip-download")
+            .arg("--write-sub")
+            .arg("--write-auto-sub")
+            .arg("--sub-lang")
+            .arg(lang)
+            .arg("--convert-subs")
+            .arg("srt")
+            .arg("-o")
+            .arg(temp_path.join("video.mp4"))
+            .arg("--no-playlist")
+            .args(yt_dlp_output_args(true))
+            .arg(input)
+            .status()
+            .context("Failed to run yt-dlp")?;
+        if !status.success() {
+            bail!("yt-dlp failed to download subtitles");
+        }
+        find_subtitle_file(temp_path, lang)
+    } else {
+        let video_path = if is_url(input) {
+            let path = temp_path.join("video");
+            download_file(input, &path)?;
+            path
+        } else {
+            PathBuf::from(input)
+        };
+        let media = probe_source(config, &video_path, false);
+        match extract_embedded_subs(&media, &ffmpeg, &video_path, temp_path)? {
+            Some(extracted) => Some(extracted),
+            None => find_adjacent_subtitle(&video_path),
+        }
+    };
+
+    let Some(sub_path) = sub_path else {
+        bail!("No subtitles found for {} (use --subs to provide them)", input);
+    };
+    let sub_path = srt::ensure_utf8(&sub_path, "auto", temp_path)?;
+    let entries = srt::merge_held_captions(srt::parse_subtitles(&sub_path)?);
+    println!("Loaded {} subtitle lines", entries.len());
+
+    let picked = srt::find_dialogue(&entries, query, exact_diacritics).ok().map(|m| m.entry);
+    let matches = srt::all_matches(&entries, query, exact_diacritics);
+    if matches.is_empty() {
+        println!("No line contains any word of \"{}\"", query);
+        return Ok(());
+    }
+
+    for (tier, m) in &matches {
+        let marker = if picked.is_some_and(|p| std::ptr::eq(p, m.entry)) { "*" } else { " " };
+        println!(
+            "{} {:<8} {:.2}  [{} - {}] {}",
+            marker,
+            format!("{:?}", tier),
+            m.score,
+            format_clock(m.entry.start),
+            format_clock(m.entry.end),
+            m.entry.text.replace('\n', " / ")
+        );
+    }
+
+    match picked {
+        Some(_) => println!("\n* = the line --from \"{}\" would use", query),
+        None => println!("\nNo line scores high enough for --from to use (fuzzy needs half the words)"),
+    }
+    Ok(())
+}
+
 fn resolve_subs_input(subs_input: &str, temp_path: &Path) -> Result<PathBuf> {
     if is_url(subs_input) {
         println!("Downloading subtitles...");
-        let ext = Path::new(subs_input)
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("srt");
+        let download = temp_path.join("subs.download");
+        let filename = download_file(subs_input, &download)?;
+        // The content decides; the served filename or URL only matter if it's unrecognised
+        let ext = srt::detect_format(&download)
+            .map(str::to_string)
+            .or_else(|| filename.as_deref().and_then(subtitle_extension))
+            .or_else(|| subtitle_extension(subs_input))
+            .unwrap_or_else(|| "srt".to_string());
         let dest = temp_path.join(format!("subs.{}", ext));
-        download_file(subs_input, &dest)?;
+        fs::rename(&download, &dest)?;
         Ok(dest)
     } else {
         let path = PathBuf::from(subs_input);
         if !path.exists() {
             bail!("Subtitle file does not exist: {}", subs_input);
         }
+
+        // A .txt, extensionless or misnamed file is parsed by its real format
+        let ext = subtitle_extension(subs_input);
+        if let Some(format) = srt::detect_format(&path) {
+            let matches = match ext.as_deref() {
+                Some("ssa") => format == "ass",
+                Some(ext) => ext == format,
+                None => false,
+            };
+            if !matches {
+                println!("Reading {} as {} subtitles", subs_input, format.to_uppercase());
+                let dest = temp_path.join(format!("subs.{}", format));
+                fs::copy(&path, &dest)
+                    .with_context(|| format!("Failed to copy {}", path.display()))?;
+                return Ok(dest);
+            }
+        }
         Ok(path)
     }
 }
 
+/// Lowercase extension of a subtitle path, URL or filename
+fn subtitle_extension(name: &str) -> Option<String> {
+    let name = name.split(['?', '#']).next().unwrap_or(name);
+    Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .filter(|e| ["srt", "ass", "ssa", "vtt", "lrc"].contains(&e.as_str()))
+}
+
 fn find_adjacent_subtitle(video_path: &Path) -> Option<PathBuf> {
     let stem = video_path.file_stem()?;
     let parent = video_path.parent()?;
 
     // Check for common subtitle extensions
-    for ext in &["srt", "ass", "ssa", "sub", "vtt"] {
+    for ext in &["srt", "ass", "ssa", "sub", "vtt", "lrc"] {
         let sub_path = parent.join(format!("{}.{}", stem.to_string_lossy(), ext));
         if sub_path.exists() {
             println!("Found adjacent subtitle file: {}", sub_path.display());
@@ -762,3 +5109,200 @@ fn find_adjacent_subtitle(video_path: &Path) -> Option<PathBuf> {
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Arguments of an encode of seconds 1-3 of video.mp4
+    fn encode_args<'a>(
+        build: impl FnOnce(&Path, &Path, &'a FilterAssets, &'a Cli, f64, f64) -> FfmpegCommand<'a>,
+        assets: &'a FilterAssets,
+        cli: &'a Cli,
+        output: &str,
+    ) -> Vec<String> {
+        build(Path::new("ffmpeg"), Path::new("video.mp4"), assets, cli, 1.0, 2.0)
+            .command(Path::new(output))
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    fn clip_assets() -> FilterAssets {
+        FilterAssets { source_start: 1.0, ..Default::default() }
+    }
+
+    // Expected vectors were recorded from the encoders before they shared FfmpegCommand,
+    // with the video formats since moved to their bilinear default scaler
+
+    #[test]
+    fn gif_arguments_match_the_original_encoder() {
+        let cli = Cli::parse_from(["gifclip", "video.mp4"]);
+        let assets = clip_assets();
+        assert_eq!(
+            encode_args(gif_command, &assets, &cli, "out.gif"),
+            "-y -ss 0 -copyts -start_at_zero -i video.mp4 -ss 1 -t 2 -filter_complex \
+             fps=15,scale=480:-1:flags=lanczos,split[s0][s1];[s0]palettegen=max_colors=208[p];[s1][p]paletteuse=dither=bayer \
+             out.gif"
+                .split_whitespace()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn mp4_arguments_match_the_original_encoder() {
+        let cli = Cli::parse_from(["gifclip", "video.mp4", "--audio"]);
+        let assets = clip_assets();
+        assert_eq!(
+            encode_args(mp4_command, &assets, &cli, "out.mp4"),
+            "-y -ss 0 -copyts -start_at_zero -i video.mp4 -ss 1 -t 2 -filter_complex fps=15,scale=480:-1:flags=bilinear \
+             -c:v libx264 -crf 19 -preset medium -c:a aac -b:a 128k -pix_fmt yuv420p -movflags +faststart out.mp4"
+                .split_whitespace()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn webm_arguments_match_the_original_encoder() {
+        let cli = Cli::parse_from(["gifclip", "video.mp4"]);
+        let assets = clip_assets();
+        assert_eq!(
+            encode_args(webm_command, &assets, &cli, "out.webm"),
+            "-y -ss 0 -copyts -start_at_zero -i video.mp4 -ss 1 -t 2 -filter_complex fps=15,scale=480:-1:flags=bilinear \
+             -c:v libvpx-vp9 -crf 21 -b:v 0 -an -pix_fmt yuv420p out.webm"
+                .split_whitespace()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn webp_arguments_match_the_original_encoder() {
+        let cli = Cli::parse_from(["gifclip", "video.mp4"]);
+        let assets = clip_assets();
+        assert_eq!(
+            encode_args(webp_command, &assets, &cli, "out.webp"),
+            "-y -ss 0 -copyts -start_at_zero -i video.mp4 -ss 1 -t 2 -filter_complex fps=15,scale=480:-1:flags=bilinear \
+             -c:v libwebp -loop 0 -compression_level 4 -an -quality 80 out.webp"
+                .split_whitespace()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn audio_only_arguments_match_the_original_encoder() {
+        let cli = Cli::parse_from(["gifclip", "video.mp4", "--audio-only"]);
+        let assets = clip_assets();
+        let build = |ffmpeg: &Path, video: &Path, assets, cli, start, duration| {
+            audio_command(ffmpeg, video, Path::new("out.mp3"), assets, cli, start, duration).unwrap()
+        };
+        assert_eq!(
+            encode_args(build, &assets, &cli, "out.mp3"),
+            "-y -ss 0 -copyts -start_at_zero -i video.mp4 -ss 1 -t 2 -vn -c:a libmp3lame -q:a 2 out.mp3"
+                .split_whitespace()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn soft_subs_arguments_match_the_original_encoder() {
+        let cli = Cli::parse_from(["gifclip", "video.mp4", "--soft-subs"]);
+        let assets = FilterAssets { soft_subs: Some(PathBuf::from("subs.srt")), ..clip_assets() };
+        assert_eq!(
+            encode_args(mp4_command, &assets, &cli, "out.mp4"),
+            "-y -ss 0 -copyts -start_at_zero -i video.mp4 -i subs.srt -ss 1 -t 2 -filter_complex fps=15,scale=480:-1:flags=bilinear[vout] \
+             -c:v libx264 -crf 19 -preset medium -map [vout] -map 1:s:0 -c:s mov_text -metadata:s:s:0 language=en \
+             -an -pix_fmt yuv420p -movflags +faststart out.mp4"
+                .split_whitespace()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn subtitles_draw_after_crop_and_before_fps_and_scale() {
+        let cli = Cli::parse_from(["gifclip", "video.mp4", "--fps", "10"]);
+        let assets = FilterAssets {
+            crop: Some(Crop { width: 640, height: 300, x: 0, y: 30 }),
+            sub_path: Some(PathBuf::from("subs.srt")),
+            ..Default::default()
+        };
+        let chain = build_video_filter(&cli, &assets, OutputFormat::Gif, "lanczos");
+        let position = |filter: &str| {
+            chain
+                .split(',')
+                .position(|f| f.starts_with(filter))
+                .unwrap_or_else(|| panic!("no {} in {}", filter, chain))
+        };
+
+        assert!(position("crop=") < position("subtitles="), "{}", chain);
+        assert!(position("subtitles=") < position("fps=10"), "{}", chain);
+        assert!(position("fps=10") < position("scale="), "{}", chain);
+    }
+
+    #[test]
+    fn config_defaults_only_fill_options_not_given() {
+        let matches = Cli::command().get_matches_from(["gifclip", "video.mp4", "--fps", "24"]);
+        let mut cli = Cli::from_arg_matches(&matches).unwrap();
+        let config = config::Config {
+            default_format: Some("mp4,webm".to_string()),
+            default_width: Some(320),
+            default_fps: Some(10),
+            ..Default::default()
+        };
+        apply_config_defaults(&mut cli, &matches, &config).unwrap();
+
+        assert_eq!(cli.formats, [OutputFormat::Mp4, OutputFormat::Webm]);
+        assert_eq!(cli.width, 320);
+        assert_eq!(cli.fps, 24);
+        assert_eq!(cli.lang, "en");
+    }
+
+    #[test]
+    fn two_part_timestamps_are_minutes_and_seconds() {
+        assert_eq!(parse_timestamp("75:30").unwrap(), 75.0 * 60.0 + 30.0);
+        assert_eq!(parse_timestamp("90:00").unwrap(), 5400.0);
+        assert_eq!(parse_timestamp("1:02.5").unwrap(), 62.5);
+    }
+
+    #[test]
+    fn three_part_timestamps_are_hours_minutes_seconds() {
+        assert_eq!(parse_timestamp("1:15:30").unwrap(), 4530.0);
+        assert!(parse_timestamp("1:75:30").is_err());
+    }
+
+    #[test]
+    fn timestamps_reject_bad_seconds_and_formats() {
+        assert_eq!(parse_timestamp("42.5").unwrap(), 42.5);
+        assert!(parse_timestamp("75:60").is_err());
+        assert!(parse_timestamp("1:2:3:4").is_err());
+        assert!(parse_timestamp("soon").is_err());
+    }
+
+    #[test]
+    fn sanitize_keeps_emoji_whole() {
+        assert_eq!(sanitize_filename("Cats 🐈‍⬛ are great!"), "Cats 🐈‍⬛ are great!");
+
+        // Truncation never splits a multi-codepoint grapheme
+        let family = "👨‍👩‍👧".repeat(60);
+        let sanitized = sanitize_filename(&family);
+        assert_eq!(sanitized.graphemes(true).count(), MAX_TITLE_LEN);
+        assert!(sanitized.graphemes(true).all(|g| g == "👨‍👩‍👧"));
+    }
+
+    #[test]
+    fn sanitize_all_special_characters() {
+        assert_eq!(sanitize_filename(r#"<>:"/\|?*"#), "video");
+        assert_eq!(sanitize_filename("  ...  "), "video");
+        assert_eq!(sanitize_filename("What? No: way"), "What_ No_ way");
+    }
+
+    #[test]
+    fn sanitize_long_title_cuts_at_a_word() {
+        let title = "The quick brown fox jumps over the lazy dog and keeps on running away.";
+        let sanitized = sanitize_filename(title);
+        assert_eq!(sanitized, "The quick brown fox jumps over the lazy dog and");
+        assert!(sanitized.graphemes(true).count() <= MAX_TITLE_LEN);
+
+        // No break to cut at: a hard cut at the limit
+        assert_eq!(sanitize_filename(&"a".repeat(80)), "a".repeat(MAX_TITLE_LEN));
+    }
+}
@@ -0,0 +1,110 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+const API_URL: &str = "https://api.opensubtitles.com/api/v1";
+
+/// Bytes hashed from each end of the file
+const HASH_CHUNK: u64 = 64 * 1024;
+
+#[derive(Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    data: Vec<SearchResult>,
+}
+
+#[derive(Deserialize)]
+struct SearchResult {
+    attributes: SearchAttributes,
+}
+
+#[derive(Deserialize)]
+struct SearchAttributes {
+    #[serde(default)]
+    moviehash_match: bool,
+    #[serde(default)]
+    files: Vec<SubtitleFile>,
+}
+
+#[derive(Deserialize)]
+struct SubtitleFile {
+    file_id: u64,
+}
+
+#[derive(Deserialize)]
+struct DownloadResponse {
+    link: String,
+}
+
+/// OpenSubtitles movie hash: the file size plus the sums of the first and last 64 KiB
+/// read as little-endian u64 words (all wrapping), as 16 hex digits
+pub fn movie_hash(path: &Path) -> Result<String> {
+    let mut file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let size = file.metadata()?.len();
+    if size < HASH_CHUNK {
+        bail!("File is too small to hash for OpenSubtitles");
+    }
+
+    let mut hash = size;
+    let mut buf = vec![0u8; HASH_CHUNK as usize];
+    for offset in [0, size - HASH_CHUNK] {
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut buf)?;
+        for word in buf.chunks_exact(8) {
+            hash = hash.wrapping_add(u64::from_le_bytes(word.try_into().unwrap()));
+        }
+    }
+
+    Ok(format!("{:016x}", hash))
+}
+
+/// Look up subtitles for a local video by its hash and save the best match as SRT.
+///
+/// Returns false when OpenSubtitles has nothing for this file in `lang`.
+pub fn fetch_subtitles(api_key: &str, video_path: &Path, lang: &str, dest: &Path) -> Result<bool> {
+    let hash = movie_hash(video_path)?;
+    let client = reqwest::blocking::Client::builder()
+        .user_agent(concat!("gifclip v", env!("CARGO_PKG_VERSION")))
+        .build()?;
+
+    let response = client
+        .get(format!("{}/subtitles", API_URL))
+        .header("Api-Key", api_key)
+        .query(&[("moviehash", hash.as_str()), ("languages", lang)])
+        .send()
+        .context("Failed to reach OpenSubtitles")?;
+    if !response.status().is_success() {
+        bail!("OpenSubtitles search failed: HTTP {}", response.status());
+    }
+    let search: SearchResponse =
+        serde_json::from_str(&response.text()?).context("Failed to parse OpenSubtitles search results")?;
+
+    // Hash matches are synced to this exact file; anything else is a guess by title
+    let Some(file_id) = search
+        .data
+        .iter()
+        .filter(|r| r.attributes.moviehash_match)
+        .find_map(|r| r.attributes.files.first())
+        .map(|f| f.file_id)
+    else {
+        return Ok(false);
+    };
+
+    let response = client
+        .post(format!("{}/download", API_URL))
+        .header("Api-Key", api_key)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(format!(r#"{{"file_id":{},"sub_format":"srt"}}"#, file_id))
+        .send()
+        .context("Failed to reach OpenSubtitles")?;
+    if !response.status().is_success() {
+        bail!("OpenSubtitles download failed: HTTP {}", response.status());
+    }
+    let download: DownloadResponse =
+        serde_json::from_str(&response.text()?).context("Failed to parse OpenSubtitles download link")?;
+
+    crate::download_file(&download.link, dest)?;
+    Ok(true)
+}
@@ -0,0 +1,220 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::Config;
+
+/// Cached probes not written for this long are deleted the next time one is stored
+const PROBE_CACHE_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// What gifclip needs to know about a source, from a single ffprobe run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MediaInfo {
+    pub duration: Option<f64>,
+    pub streams: Vec<StreamInfo>,
+    /// Why probing failed, if it did; never cached
+    #[serde(skip)]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamInfo {
+    /// Index among all streams in the file, as in `-map 0:N`
+    pub index: usize,
+    /// "video", "audio", "subtitle", ...
+    pub kind: String,
+    pub codec: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<f64>,
+    pub frame_count: Option<u64>,
+    pub sample_rate: Option<u32>,
+    /// Language tag, empty when untagged
+    pub language: String,
+    /// Clockwise rotation from the display matrix or the legacy rotate tag
+    pub rotation: Option<f64>,
+    pub color_transfer: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FfprobeOutput {
+    #[serde(default)]
+    format: Option<FfprobeFormat>,
+    #[serde(default)]
+    streams: Vec<FfprobeStream>,
+}
+
+#[derive(Deserialize)]
+struct FfprobeFormat {
+    duration: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FfprobeStream {
+    index: usize,
+    #[serde(default)]
+    codec_type: String,
+    #[serde(default)]
+    codec_name: String,
+    width: Option<u32>,
+    height: Option<u32>,
+    r_frame_rate: Option<String>,
+    nb_frames: Option<String>,
+    sample_rate: Option<String>,
+    color_transfer: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+    #[serde(default)]
+    side_data_list: Vec<serde_json::Value>,
+}
+
+impl MediaInfo {
+    /// Probe `path`, reusing an earlier run's result from the cache directory when `cached`
+    /// and the file's size and modification time haven't changed
+    pub fn load(config: &Config, path: &Path, cached: bool) -> Result<Self> {
+        let cache_path = if cached { cache_path(path) } else { None };
+        if let Some(ref cache) = cache_path
+            && let Some(info) = fs::read_to_string(cache)
+                .ok()
+                .and_then(|content| serde_json::from_str(&content).ok())
+        {
+            return Ok(info);
+        }
+
+        let info = Self::probe(&config.ffprobe_path()?, path)?;
+
+        if let Some(cache) = cache_path {
+            let stored = cache
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::write(&cache, serde_json::to_string(&info).unwrap_or_default()));
+            if let Err(e) = stored {
+                eprintln!("Warning: Could not cache probe results: {}", e);
+            }
+            if let Some(dir) = cache.parent() {
+                prune_cache(dir);
+            }
+        }
+        Ok(info)
+    }
+
+    fn probe(ffprobe: &Path, path: &Path) -> Result<Self> {
+        let output = Command::new(ffprobe)
+            .args(["-v", "error", "-show_format", "-show_streams", "-of", "json"])
+            .arg(path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .output()
+            .context("Failed to run ffprobe")?;
+
+        if !output.status.success() {
+            bail!("ffprobe couldn't read {}", path.display());
+        }
+
+        let parsed: FfprobeOutput =
+            serde_json::from_slice(&output.stdout).context("Failed to parse ffprobe output")?;
+
+        let streams = parsed
+            .streams
+            .into_iter()
+            .map(|s| {
+                // The display matrix angle is counter-clockwise (-90 for a typical portrait
+                // phone video); the tag is clockwise
+                let rotation = s
+                    .side_data_list
+                    .iter()
+                    .find_map(|data| data.get("rotation").and_then(serde_json::Value::as_f64))
+                    .map(|degrees| -degrees)
+                    .or_else(|| s.tags.get("rotate").and_then(|r| r.trim().parse().ok()));
+                StreamInfo {
+                    index: s.index,
+                    kind: s.codec_type,
+                    codec: s.codec_name,
+                    width: s.width,
+                    height: s.height,
+                    frame_rate: s.r_frame_rate.as_deref().and_then(parse_rate),
+                    frame_count: s.nb_frames.and_then(|n| n.parse().ok()),
+                    sample_rate: s.sample_rate.and_then(|r| r.parse().ok()),
+                    language: s.tags.get("language").cloned().unwrap_or_default(),
+                    rotation,
+                    color_transfer: s.color_transfer,
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            duration: parsed.format.and_then(|f| f.duration).and_then(|d| d.parse().ok()),
+            streams,
+            error: None,
+        })
+    }
+
+    /// Streams of one kind, in file order (so `.nth(n)` matches ffprobe's `a:n`)
+    pub fn streams_of<'a>(&'a self, kind: &'a str) -> impl Iterator<Item = &'a StreamInfo> {
+        self.streams.iter().filter(move |s| s.kind == kind)
+    }
+
+    /// The first video stream, which ffmpeg picks by default
+    pub fn video(&self) -> Option<&StreamInfo> {
+        self.streams_of("video").next()
+    }
+
+    /// Error message for something the probe didn't find: the probe's own failure when it
+    /// failed, since that's the real cause, otherwise `missing`
+    pub fn missing(&self, missing: &str) -> String {
+        match self.error {
+            Some(ref error) => format!("Could not probe the source: {}", error),
+            None => missing.to_string(),
+        }
+    }
+}
+
+/// Rational like "30000/1001"
+fn parse_rate(rate: &str) -> Option<f64> {
+    let fps = match rate.split_once('/') {
+        Some((num, den)) => match (num.parse::<f64>(), den.parse::<f64>()) {
+            (Ok(num), Ok(den)) if den > 0.0 => num / den,
+            _ => return None,
+        },
+        None => rate.parse().ok()?,
+    };
+    (fps > 0.0).then_some(fps)
+}
+
+/// Delete cached probes older than `PROBE_CACHE_MAX_AGE`; entries for edited or deleted
+/// files are never read again, so age is the only way they go
+fn prune_cache(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let now = SystemTime::now();
+    for entry in entries.flatten() {
+        let stale = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .is_ok_and(|modified| now.duration_since(modified).unwrap_or_default() > PROBE_CACHE_MAX_AGE);
+        if stale {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// Cache entries are keyed by absolute path, size and modification time, so an edited or
+/// replaced file is probed again
+fn cache_path(path: &Path) -> Option<PathBuf> {
+    let absolute = path.canonicalize().ok()?;
+    let metadata = fs::metadata(&absolute).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+
+    let mut hasher = DefaultHasher::new();
+    absolute.hash(&mut hasher);
+    metadata.len().hash(&mut hasher);
+    modified.hash(&mut hasher);
+    Some(Config::cache_dir().ok()?.join("probe").join(format!("{:016x}.json", hasher.finish())))
+}
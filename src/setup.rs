@@ -1,8 +1,9 @@
 use anyhow::{bail, Context, Result};
 use dialoguer::Select;
 use std::fs;
-use std::io::{self, Write};
-use std::path::Path;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
@@ -14,9 +15,39 @@ use tar::Archive;
 
 use crate::config::{Config, ToolSource};
 
-pub fn run_setup() -> Result<Config> {
+/// Choose where the tools come from and save the config. With a `source` (from
+/// --setup-source) nothing is asked, so this also works without a terminal.
+pub fn run_setup(source: Option<ToolSource>) -> Result<Config> {
     println!("gifclip setup\n");
 
+    let tool_source = match source {
+        Some(source) => source,
+        None => prompt_tool_source()?,
+    };
+
+    // Keep any other settings from an existing config
+    let config = Config {
+        tool_source,
+        ..Config::load().unwrap_or_default()
+    };
+
+    if config.tool_source == ToolSource::Managed {
+        download_tools(&config)?;
+    } else if let Some((name, _)) = tool_paths(&config).iter().find(|(_, path)| path.is_err()) {
+        println!("Note: {} isn't on PATH yet; install it before clipping", name);
+    }
+
+    config.save()?;
+    println!("\nConfiguration saved to {}", Config::config_path()?.display());
+
+    Ok(config)
+}
+
+fn prompt_tool_source() -> Result<ToolSource> {
+    if !io::stdin().is_terminal() {
+        bail!("Setup needs a terminal to ask questions; use --setup-source system or --setup-source managed");
+    }
+
     let has_system_ytdlp = which::which("yt-dlp").is_ok();
     let has_system_ffmpeg = which::which("ffmpeg").is_ok();
     let has_system_ffprobe = which::which("ffprobe").is_ok();
@@ -74,22 +105,11 @@ pub fn run_setup() -> Result<Config> {
         if choice == 0 { 1 } else { 0 }
     };
 
-    let tool_source = if choice == 0 {
+    Ok(if choice == 0 {
         ToolSource::System
     } else {
         ToolSource::Managed
-    };
-
-    let config = Config { tool_source };
-
-    if config.tool_source == ToolSource::Managed {
-        download_tools(&config)?;
-    }
-
-    config.save()?;
-    println!("\nConfiguration saved to {}", Config::config_path()?.display());
-
-    Ok(config)
+    })
 }
 
 pub fn ensure_setup() -> Result<Config> {
@@ -105,19 +125,32 @@ pub fn ensure_setup() -> Result<Config> {
         if config.tool_source == ToolSource::Managed {
             // Tools should be managed but missing - redownload
             println!("Managed tools missing, downloading...");
+            for (name, path) in tool_paths(&config) {
+                if let Ok(path) = path
+                    && !path.exists()
+                {
+                    println!("  {}: expected at {}", name, path.display());
+                }
+            }
             download_tools(&config)?;
             return Ok(config);
         }
 
-        // No config or system tools missing - run interactive setup
+        // No config or system tools missing - run interactive setup, if there's anyone to ask
+        if !io::stdin().is_terminal() {
+            bail!(
+                "gifclip requires yt-dlp, ffmpeg, and ffprobe, and some are missing from PATH. \
+                 Install them, or run `gifclip --setup-source managed` to download them."
+            );
+        }
         println!("gifclip requires yt-dlp, ffmpeg, and ffprobe to work.\n");
-        return run_setup();
+        return run_setup(None);
     }
 
     Ok(config)
 }
 
-fn download_tools(_config: &Config) -> Result<()> {
+fn download_tools(config: &Config) -> Result<()> {
     let tools_dir = Config::tools_dir()?;
     fs::create_dir_all(&tools_dir)
         .with_context(|| format!("Failed to create tools directory: {}", tools_dir.display()))?;
@@ -127,11 +160,125 @@ fn download_tools(_config: &Config) -> Result<()> {
     download_ytdlp(&tools_dir)?;
     download_ffmpeg(&tools_dir)?;
 
+    verify_tools(config)?;
+
     println!("Tools installed successfully!");
 
     Ok(())
 }
 
+pub fn tool_paths(config: &Config) -> [(&'static str, Result<PathBuf>); 3] {
+    [
+        ("yt-dlp", config.yt_dlp_path()),
+        ("ffmpeg", config.ffmpeg_path()),
+        ("ffprobe", config.ffprobe_path()),
+    ]
+}
+
+/// First line of a tool's version output, or None if it doesn't run
+pub fn tool_version(name: &str, path: &Path) -> Option<String> {
+    // yt-dlp takes --version, ffmpeg/ffprobe take -version
+    let version_flag = if name == "yt-dlp" { "--version" } else { "-version" };
+    let output = Command::new(path)
+        .arg(version_flag)
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let version = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty());
+    Some(version.unwrap_or_else(|| "unknown version".to_string()))
+}
+
+/// Print the settings file, every setting and where each tool resolves to, for
+/// `gifclip config show`. Never prompts or downloads anything.
+pub fn show_config(json: bool) -> Result<()> {
+    let config = Config::load()?;
+    let config_path = Config::config_path()?;
+
+    let tools: Vec<(&str, Result<PathBuf>, Option<String>)> = tool_paths(&config)
+        .into_iter()
+        .map(|(name, path)| {
+            let version = path.as_ref().ok().and_then(|p| tool_version(name, p));
+            (name, path, version)
+        })
+        .collect();
+
+    if json {
+        let settings: serde_json::Map<String, serde_json::Value> = config
+            .settings()
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.into()))
+            .collect();
+        let tools: serde_json::Map<String, serde_json::Value> = tools
+            .iter()
+            .map(|(name, path, version)| {
+                let tool = match path {
+                    Ok(path) => serde_json::json!({
+                        "path": path,
+                        "exists": path.exists(),
+                        "version": version,
+                    }),
+                    Err(e) => serde_json::json!({ "path": null, "exists": false, "error": e.to_string() }),
+                };
+                (name.to_string(), tool)
+            })
+            .collect();
+        let report = serde_json::json!({
+            "config_path": config_path,
+            "config_exists": config_path.exists(),
+            "settings": settings,
+            "tools": tools,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    let state = if config_path.exists() { "" } else { " (not created yet; using defaults)" };
+    println!("Config file: {}{}", config_path.display(), state);
+    println!("\nSettings:");
+    for (key, value) in config.settings() {
+        println!("  {:<22} {}", key, value.as_deref().unwrap_or("-"));
+    }
+
+    println!("\nTools:");
+    for (name, path, version) in &tools {
+        let status = match (path, version) {
+            (Err(e), _) => format!("{}", e),
+            (Ok(path), _) if !path.exists() => format!("{} (missing)", path.display()),
+            (Ok(path), Some(version)) => format!("{} ({})", path.display(), version),
+            (Ok(path), None) => format!("{} (doesn't run)", path.display()),
+        };
+        println!("  {:<8} {}", name, status);
+    }
+
+    Ok(())
+}
+
+/// Confirm each managed binary exists under its expected name and actually runs.
+fn verify_tools(config: &Config) -> Result<()> {
+    for (name, path) in tool_paths(config) {
+        let path = path?;
+        if !path.exists() {
+            bail!(
+                "{} was downloaded but not found at {}. The archive may use a different binary name; install {} manually or re-run --setup.",
+                name,
+                path.display(),
+                name
+            );
+        }
+
+        if tool_version(name, &path).is_none() {
+            bail!("{} at {} is not runnable (its version check failed)", name, path.display());
+        }
+    }
+
+    Ok(())
+}
+
 fn download_ytdlp(tools_dir: &Path) -> Result<()> {
     print!("Downloading yt-dlp... ");
     io::stdout().flush()?;
@@ -210,30 +357,98 @@ fn download_ffmpeg(tools_dir: &Path) -> Result<()> {
         target_os = "windows"
     ))]
     {
-        let response = reqwest::blocking::get(url)
-            .context("Failed to download ffmpeg")?;
-
-        if !response.status().is_success() {
-            bail!("Failed to download ffmpeg: HTTP {}", response.status());
-        }
+        // The archive is large, so keep partial progress across interrupted runs
+        let archive_path = tools_dir.join("ffmpeg-download");
+        download_resumable(url, &archive_path).context("Failed to download ffmpeg")?;
 
-        let bytes = response.bytes().context("Failed to read ffmpeg download")?;
+        let bytes = fs::read(&archive_path)
+            .with_context(|| format!("Failed to read {}", archive_path.display()))?;
 
         #[cfg(target_os = "linux")]
         extract_ffmpeg_linux(&bytes, tools_dir)?;
 
         #[cfg(target_os = "macos")]
-        extract_ffmpeg_macos(&bytes, tools_dir)?;
+        {
+            extract_binary_macos(&bytes, tools_dir, "ffmpeg")?;
+
+            // evermeet.cx ships ffprobe as a separate download
+            let response = reqwest::blocking::get(FFPROBE_MACOS_URL)
+                .context("Failed to download ffprobe")?;
+
+            if !response.status().is_success() {
+                bail!("Failed to download ffprobe: HTTP {}", response.status());
+            }
+
+            let bytes = response.bytes().context("Failed to read ffprobe download")?;
+            extract_binary_macos(&bytes, tools_dir, "ffprobe")?;
+        }
 
         #[cfg(target_os = "windows")]
         extract_ffmpeg_windows(&bytes, tools_dir)?;
 
+        fs::remove_file(&archive_path)
+            .with_context(|| format!("Failed to remove {}", archive_path.display()))?;
+
         println!("done");
     }
 
     Ok(())
 }
 
+/// Download `url` to `dest`, resuming from `<dest>.part` if an earlier attempt was interrupted.
+#[cfg(any(
+    all(target_os = "linux", target_arch = "x86_64"),
+    all(target_os = "linux", target_arch = "aarch64"),
+    target_os = "macos",
+    target_os = "windows"
+))]
+fn download_resumable(url: &str, dest: &Path) -> Result<()> {
+    use reqwest::StatusCode;
+    use reqwest::header::RANGE;
+    use std::fs::OpenOptions;
+
+    let mut part_path = dest.as_os_str().to_owned();
+    part_path.push(".part");
+    let part_path = PathBuf::from(part_path);
+
+    let existing = fs::metadata(&part_path).map(|m| m.len()).unwrap_or(0);
+
+    let mut request = reqwest::blocking::Client::new().get(url);
+    if existing > 0 {
+        request = request.header(RANGE, format!("bytes={}-", existing));
+    }
+
+    let mut response = request.send().with_context(|| format!("Failed to request {}", url))?;
+    let status = response.status();
+
+    let mut file = if status == StatusCode::PARTIAL_CONTENT {
+        OpenOptions::new()
+            .append(true)
+            .open(&part_path)
+            .with_context(|| format!("Failed to open {}", part_path.display()))?
+    } else if status == StatusCode::RANGE_NOT_SATISFIABLE && existing > 0 {
+        // The partial file already holds the whole download
+        fs::rename(&part_path, dest)
+            .with_context(|| format!("Failed to move download to {}", dest.display()))?;
+        return Ok(());
+    } else if status.is_success() {
+        // Server ignored the range (or nothing to resume) - start over
+        File::create(&part_path)
+            .with_context(|| format!("Failed to create {}", part_path.display()))?
+    } else {
+        bail!("HTTP {}", status);
+    };
+
+    io::copy(&mut response, &mut file)
+        .with_context(|| format!("Download interrupted; progress kept in {}", part_path.display()))?;
+    drop(file);
+
+    fs::rename(&part_path, dest)
+        .with_context(|| format!("Failed to move download to {}", dest.display()))?;
+
+    Ok(())
+}
+
 #[cfg(target_os = "linux")]
 fn extract_ffmpeg_linux(bytes: &[u8], tools_dir: &Path) -> Result<()> {
     use std::io::Cursor;
@@ -291,31 +506,23 @@ fn extract_ffmpeg_linux(bytes: &[u8], tools_dir: &Path) -> Result<()> {
 }
 
 #[cfg(target_os = "macos")]
-fn extract_ffmpeg_macos(bytes: &[u8], tools_dir: &Path) -> Result<()> {
+const FFPROBE_MACOS_URL: &str = "https://evermeet.cx/ffmpeg/getrelease/ffprobe/zip";
+
+/// Extract a single binary from an evermeet.cx zip (each zip holds one tool).
+#[cfg(target_os = "macos")]
+fn extract_binary_macos(bytes: &[u8], tools_dir: &Path, binary: &str) -> Result<()> {
     use std::io::Cursor;
 
     let cursor = Cursor::new(bytes);
     let mut archive = zip::ZipArchive::new(cursor)?;
-
-    let mut found_ffmpeg = false;
-    let mut found_ffprobe = false;
+    let suffix = format!("/{}", binary);
 
     for i in 0..archive.len() {
         let mut file = archive.by_index(i)?;
         let name = file.name();
 
-        if name == "ffmpeg" || name.ends_with("/ffmpeg") {
-            let dest = tools_dir.join("ffmpeg");
-            let mut outfile = File::create(&dest)?;
-            io::copy(&mut file, &mut outfile)?;
-
-            let mut perms = fs::metadata(&dest)?.permissions();
-            perms.set_mode(0o755);
-            fs::set_permissions(&dest, perms)?;
-
-            found_ffmpeg = true;
-        } else if name == "ffprobe" || name.ends_with("/ffprobe") {
-            let dest = tools_dir.join("ffprobe");
+        if name == binary || name.ends_with(&suffix) {
+            let dest = tools_dir.join(binary);
             let mut outfile = File::create(&dest)?;
             io::copy(&mut file, &mut outfile)?;
 
@@ -323,22 +530,11 @@ fn extract_ffmpeg_macos(bytes: &[u8], tools_dir: &Path) -> Result<()> {
             perms.set_mode(0o755);
             fs::set_permissions(&dest, perms)?;
 
-            found_ffprobe = true;
-        }
-
-        if found_ffmpeg && found_ffprobe {
             return Ok(());
         }
     }
 
-    if !found_ffmpeg {
-        bail!("ffmpeg binary not found in archive");
-    }
-    if !found_ffprobe {
-        bail!("ffprobe binary not found in archive");
-    }
-
-    Ok(())
+    bail!("{} binary not found in archive", binary)
 }
 
 #[cfg(target_os = "windows")]
@@ -1,15 +1,125 @@
 use anyhow::{bail, Context, Result};
 use regex::Regex;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
 
 #[derive(Debug, Clone)]
 pub struct SubtitleEntry {
+    /// 1-based cue number: the SRT's own numbering, or the position in other formats
+    pub index: usize,
     pub start: f64,
     pub end: f64,
     pub text: String,
 }
 
+/// Make sure a subtitle file is UTF-8, writing a decoded copy into `temp_dir` if not.
+///
+/// `encoding` is a WHATWG label (e.g. "windows-1252", "shift_jis") or "auto" to detect it.
+/// Returns the path to read and burn from, which is the original file when already UTF-8.
+pub fn ensure_utf8(path: &Path, encoding: &str, temp_dir: &Path) -> Result<PathBuf> {
+    let bytes = fs::read(path)
+        .with_context(|| format!("Failed to read subtitle file: {}", path.display()))?;
+
+    let encoding = if encoding.eq_ignore_ascii_case("auto") {
+        if std::str::from_utf8(&bytes).is_ok() {
+            return Ok(path.to_path_buf());
+        }
+        let mut detector = chardetng::EncodingDetector::new();
+        detector.feed(&bytes, true);
+        detector.guess(None, true)
+    } else {
+        encoding_rs::Encoding::for_label(encoding.as_bytes())
+            .with_context(|| format!("Unknown subtitle encoding: {}", encoding))?
+    };
+
+    if encoding == encoding_rs::UTF_8 && std::str::from_utf8(&bytes).is_ok() {
+        return Ok(path.to_path_buf());
+    }
+
+    let (decoded, _, had_errors) = encoding.decode(&bytes);
+    if had_errors {
+        eprintln!(
+            "Warning: some subtitle characters could not be decoded as {}",
+            encoding.name()
+        );
+    }
+
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("srt");
+    let dest = temp_dir.join(format!("subs-utf8.{}", ext));
+    fs::write(&dest, decoded.as_bytes())
+        .with_context(|| format!("Failed to write {}", dest.display()))?;
+
+    println!("Converted subtitles from {} to UTF-8", encoding.name());
+    Ok(dest)
+}
+
+/// Subtitle format judged from the content ("srt", "vtt", "ass" or "lrc"), for files whose
+/// extension is missing or wrong. None if it doesn't look like any of them.
+pub fn detect_format(path: &Path) -> Option<&'static str> {
+    let bytes = fs::read(path).ok()?;
+    let content = String::from_utf8_lossy(&bytes);
+    let content = content.trim_start_matches('\u{feff}').trim_start();
+
+    if content.starts_with("WEBVTT") {
+        return Some("vtt");
+    }
+    if content.starts_with("[Script Info]") || content.contains("\n[Events]") {
+        return Some("ass");
+    }
+    // SRT uses a comma before the milliseconds, VTT a period
+    if Regex::new(r"\d{2}:\d{2}:\d{2},\d{3}\s*-->").unwrap().is_match(content) {
+        return Some("srt");
+    }
+    if Regex::new(r"\d{2}:\d{2}\.\d{3}\s*-->").unwrap().is_match(content) {
+        return Some("vtt");
+    }
+    if Regex::new(r"(?m)^\[\d+:\d{2}(?:[.:]\d{1,3})?\]").unwrap().is_match(content) {
+        return Some("lrc");
+    }
+    None
+}
+
+/// Parse a subtitle file, choosing the format from its extension (SRT if unknown)
+pub fn parse_subtitles(path: &Path) -> Result<Vec<SubtitleEntry>> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "vtt" => crate::vtt::parse_vtt(path),
+        "ass" | "ssa" => crate::ass::parse_ass(path),
+        "lrc" => crate::lrc::parse_lrc(path),
+        _ => parse_srt(path),
+    }
+}
+
+/// Cues further apart than this are separate lines, even with the same text
+const HELD_CAPTION_GAP: f64 = 0.5;
+
+/// Merge consecutive cues with identical text (a caption held on screen but split across
+/// cues for timing) into one cue spanning them, so dialogue matches cover the whole line.
+/// The merged cue keeps the first one's number; cue lookups should use the unmerged list
+pub fn merge_held_captions(entries: Vec<SubtitleEntry>) -> Vec<SubtitleEntry> {
+    let mut merged: Vec<SubtitleEntry> = Vec::with_capacity(entries.len());
+    for entry in entries {
+        if let Some(last) = merged.last_mut()
+            && last.text == entry.text
+            && entry.start >= last.start
+            && entry.start - last.end <= HELD_CAPTION_GAP
+        {
+            last.end = last.end.max(entry.end);
+            continue;
+        }
+        merged.push(entry);
+    }
+    merged
+}
+
 pub fn parse_srt(path: &Path) -> Result<Vec<SubtitleEntry>> {
     let content = fs::read_to_string(path)
         .with_context(|| format!("Failed to read subtitle file: {}", path.display()))?;
@@ -22,7 +132,8 @@ pub fn parse_srt(path: &Path) -> Result<Vec<SubtitleEntry>> {
 
     for block in blocks {
         let lines: Vec<&str> = block.lines().collect();
-        if lines.len() < 3 {
+        // A timing line and at least one line of text; the cue number is optional
+        if lines.len() < 2 {
             continue;
         }
 
@@ -42,6 +153,12 @@ pub fn parse_srt(path: &Path) -> Result<Vec<SubtitleEntry>> {
             continue;
         };
 
+        // The cue number sits on the line above the timing; fall back to counting
+        let index = text_start
+            .checked_sub(2)
+            .and_then(|i| lines[i].trim().trim_start_matches('\u{feff}').parse().ok())
+            .unwrap_or(entries.len() + 1);
+
         let Some(caps) = time_re.captures(ts_line) else {
             continue;
         };
@@ -57,12 +174,11 @@ pub fn parse_srt(path: &Path) -> Result<Vec<SubtitleEntry>> {
             .replace("<b>", "")
             .replace("</b>", "")
             .replace("<u>", "")
-            .replace("</u>", "")
-            .trim()
-            .to_string();
+            .replace("</u>", "");
+        let text = decode_html_entities(&text).trim().to_string();
 
         if !text.is_empty() {
-            entries.push(SubtitleEntry { start, end, text });
+            entries.push(SubtitleEntry { index, start, end, text });
         }
     }
 
@@ -78,47 +194,265 @@ fn parse_srt_time(hours: &str, mins: &str, secs: &str, millis: &str) -> f64 {
     h * 3600.0 + m * 60.0 + s + ms / 1000.0
 }
 
-/// Find a subtitle entry containing the given text (case-insensitive fuzzy match)
-pub fn find_dialogue<'a>(entries: &'a [SubtitleEntry], query: &str) -> Result<&'a SubtitleEntry> {
-    let query_lower = query.to_lowercase();
-    let query_words: Vec<&str> = query_lower.split_whitespace().collect();
+/// Longest entity name we look for between `&` and `;`
+const MAX_ENTITY_LEN: usize = 10;
 
-    // First try: exact substring match
-    for entry in entries {
-        if entry.text.to_lowercase().contains(&query_lower) {
-            return Ok(entry);
+/// Decode the HTML entities that leak into subtitle text (YouTube captions especially):
+/// named ones like `&amp;` and `&quot;` and numeric ones like `&#39;`. Direction marks
+/// (`&lrm;`, `&rlm;`) are dropped; anything unrecognised is left alone.
+pub fn decode_html_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|&end| end <= MAX_ENTITY_LEN)
+            .and_then(|end| Some((decode_entity(&rest[1..=end])?, end + 2)));
+        match decoded {
+            Some((replacement, len)) => {
+                out.push_str(replacement.as_str());
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
         }
     }
+    out.push_str(rest);
+    out
+}
+
+fn decode_entity(name: &str) -> Option<String> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return match char::from_u32(code)? {
+            '\u{200e}' | '\u{200f}' => Some(String::new()),
+            c => Some(c.to_string()),
+        };
+    }
 
-    // Second try: all words present in order (handles line breaks in subs)
-    for entry in entries {
-        let text_lower = entry.text.to_lowercase();
-        let mut last_pos = 0;
-        let mut all_found = true;
+    let decoded = match name {
+        "amp" => "&",
+        "lt" => "<",
+        "gt" => ">",
+        "quot" => "\"",
+        "apos" => "'",
+        "nbsp" => " ",
+        "lrm" | "rlm" => "",
+        "hellip" => "…",
+        "mdash" => "—",
+        "ndash" => "–",
+        "lsquo" => "‘",
+        "rsquo" => "’",
+        "ldquo" => "“",
+        "rdquo" => "”",
+        _ => return None,
+    };
+    Some(decoded.to_string())
+}
 
-        for word in &query_words {
-            if let Some(pos) = text_lower[last_pos..].find(word) {
-                last_pos += pos + word.len();
-            } else {
-                all_found = false;
-                break;
+/// Whether an SRT or VTT file has entities that the subtitles filter would draw literally
+pub fn has_html_entities(path: &Path) -> bool {
+    let is_text_format = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("srt") || e.eq_ignore_ascii_case("vtt"));
+    is_text_format
+        && fs::read_to_string(path).is_ok_and(|content| decode_html_entities(&content) != content)
+}
+
+/// Write entries out as an SRT file (e.g. after shifting or rewriting them).
+///
+/// Overlapping cues are kept as they are, so the subtitles filter still shows them
+/// together (stacked, in file order). Cues are numbered in start order, as the format
+/// expects, and blank lines inside a cue's text are dropped since they would end the cue.
+pub fn write_srt(entries: &[SubtitleEntry], path: &Path) -> Result<()> {
+    write_cues(entries, path, false)
+}
+
+/// Write entries out as a WebVTT file, with the same ordering and cleanup as `write_srt`
+pub fn write_vtt(entries: &[SubtitleEntry], path: &Path) -> Result<()> {
+    write_cues(entries, path, true)
+}
+
+fn write_cues(entries: &[SubtitleEntry], path: &Path, vtt: bool) -> Result<()> {
+    let mut ordered: Vec<&SubtitleEntry> = entries.iter().collect();
+    // Stable, so cues starting together keep their relative order
+    ordered.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    let time = |secs: f64| {
+        let formatted = format_srt_time(secs);
+        if vtt { formatted.replace(',', ".") } else { formatted }
+    };
+
+    let mut out = String::from(if vtt { "WEBVTT\n\n" } else { "" });
+    for (i, entry) in ordered.into_iter().enumerate() {
+        let text: Vec<&str> = entry.text.lines().filter(|line| !line.trim().is_empty()).collect();
+        out.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            time(entry.start),
+            time(entry.end.max(entry.start)),
+            text.join("\n")
+        ));
+    }
+
+    fs::write(path, out).with_context(|| format!("Failed to write subtitle file: {}", path.display()))
+}
+
+/// Cut entries down to the given source time ranges, joined end to end as a clip plays
+/// them: cues are clipped to each range and retimed to start from 0
+pub fn trim_to_segments(entries: &[SubtitleEntry], segments: &[(f64, f64)]) -> Vec<SubtitleEntry> {
+    let mut trimmed = Vec::new();
+    let mut offset = 0.0;
+    for &(start, end) in segments {
+        for entry in entries {
+            if entry.end > start && entry.start < end {
+                trimmed.push(SubtitleEntry {
+                    index: trimmed.len() + 1,
+                    start: entry.start.max(start) - start + offset,
+                    end: entry.end.min(end) - start + offset,
+                    text: entry.text.clone(),
+                });
             }
         }
+        offset += end - start;
+    }
+    trimmed
+}
+
+/// Greedily rewrap text so no line exceeds `max_chars` characters (graphemes), breaking
+/// only between words. A single word longer than the limit gets a line to itself.
+pub fn wrap_text(text: &str, max_chars: usize) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    let mut line_len = 0;
 
-        if all_found {
-            return Ok(entry);
+    for word in text.split_whitespace() {
+        let word_len = word.graphemes(true).count();
+        if line_len > 0 && line_len + 1 + word_len > max_chars {
+            lines.push(std::mem::take(&mut line));
+            line_len = 0;
+        }
+        if line_len > 0 {
+            line.push(' ');
+            line_len += 1;
+        }
+        line.push_str(word);
+        line_len += word_len;
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines.join("\n")
+}
+
+fn format_srt_time(secs: f64) -> String {
+    let millis = (secs.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Scores below this are reported to the user as low-confidence matches
+pub const LOW_CONFIDENCE: f64 = 0.75;
+
+/// A subtitle entry matched by `find_dialogue`, with how confident the match is
+#[derive(Debug, Clone, Copy)]
+pub struct DialogueMatch<'a> {
+    pub entry: &'a SubtitleEntry,
+    /// Fraction of query words found in the entry (1.0 for exact/in-order matches)
+    pub score: f64,
+}
+
+impl<'a> DialogueMatch<'a> {
+    pub fn is_low_confidence(&self) -> bool {
+        self.score < LOW_CONFIDENCE
+    }
+}
+
+/// Which of `find_dialogue`'s strategies matched an entry, in the order they're tried
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchTier {
+    /// The query appears verbatim
+    Exact,
+    /// Every query word appears, in order (handles line breaks in subs)
+    InOrder,
+    /// Some query words appear; needs at least half to be picked
+    Fuzzy,
+}
+
+/// Lowercase text for dialogue matching, also dropping accents (so "cafe" finds "café")
+/// unless `exact_diacritics`
+pub fn search_key(text: &str, exact_diacritics: bool) -> String {
+    let lower = text.to_lowercase();
+    if exact_diacritics {
+        return lower;
+    }
+    lower.nfd().filter(|c| !is_combining_mark(*c)).nfc().collect()
+}
+
+/// Each entry's `search_key`, computed once per search rather than once per strategy
+fn search_keys(entries: &[SubtitleEntry], exact_diacritics: bool) -> Vec<String> {
+    entries.iter().map(|entry| search_key(&entry.text, exact_diacritics)).collect()
+}
+
+fn matches_in_order(text_lower: &str, query_words: &[&str]) -> bool {
+    let mut last_pos = 0;
+    for word in query_words {
+        match text_lower[last_pos..].find(word) {
+            Some(pos) => last_pos += pos + word.len(),
+            None => return false,
+        }
+    }
+    true
+}
+
+fn fuzzy_word_count(text_lower: &str, query_words: &[&str]) -> usize {
+    query_words.iter().filter(|w| text_lower.contains(*w)).count()
+}
+
+/// Find a subtitle entry containing the given text (case- and accent-insensitive fuzzy match)
+pub fn find_dialogue<'a>(
+    entries: &'a [SubtitleEntry],
+    query: &str,
+    exact_diacritics: bool,
+) -> Result<DialogueMatch<'a>> {
+    let query_lower = search_key(query, exact_diacritics);
+    let query_words: Vec<&str> = query_lower.split_whitespace().collect();
+    let keys = search_keys(entries, exact_diacritics);
+
+    // First try: exact substring match
+    for (entry, key) in entries.iter().zip(&keys) {
+        if key.contains(&query_lower) {
+            return Ok(DialogueMatch { entry, score: 1.0 });
+        }
+    }
+
+    // Second try: all words present in order (handles line breaks in subs)
+    for (entry, key) in entries.iter().zip(&keys) {
+        if matches_in_order(key, &query_words) {
+            return Ok(DialogueMatch { entry, score: 1.0 });
         }
     }
 
     // Third try: fuzzy - most words present
     let mut best_match: Option<(&SubtitleEntry, usize)> = None;
 
-    for entry in entries {
-        let text_lower = entry.text.to_lowercase();
-        let matches = query_words
-            .iter()
-            .filter(|w| text_lower.contains(*w))
-            .count();
+    for (entry, key) in entries.iter().zip(&keys) {
+        let matches = fuzzy_word_count(key, &query_words);
 
         if matches > 0 {
             if let Some((_, best_count)) = best_match {
@@ -131,11 +465,226 @@ pub fn find_dialogue<'a>(entries: &'a [SubtitleEntry], query: &str) -> Result<&'
         }
     }
 
-    if let Some((entry, matches)) = best_match {
-        if matches >= query_words.len() / 2 {
-            return Ok(entry);
+    if let Some((entry, matches)) = best_match
+        && matches * 2 >= query_words.len()
+    {
+        let score = matches as f64 / query_words.len() as f64;
+        return Ok(DialogueMatch { entry, score });
+    }
+
+    // Too weak to use, but the closest line usually shows what to fix in the query
+    match best_match {
+        Some((entry, matches)) => bail!(
+            "Could not find dialogue: \"{}\". Closest line ({} of {} words) at {:.1}s: \"{}\"",
+            query,
+            matches,
+            query_words.len(),
+            entry.start,
+            entry.text
+        ),
+        None => bail!("Could not find dialogue: \"{}\"", query),
+    }
+}
+
+/// Every entry each strategy would accept, for explaining why `find_dialogue` picks what it does.
+/// An entry is listed once, under the first strategy that accepts it
+pub fn all_matches<'a>(
+    entries: &'a [SubtitleEntry],
+    query: &str,
+    exact_diacritics: bool,
+) -> Vec<(MatchTier, DialogueMatch<'a>)> {
+    let query_lower = search_key(query, exact_diacritics);
+    let query_words: Vec<&str> = query_lower.split_whitespace().collect();
+
+    let keys = search_keys(entries, exact_diacritics);
+
+    let mut matches = Vec::new();
+    let mut reported = vec![false; entries.len()];
+    for tier in [MatchTier::Exact, MatchTier::InOrder, MatchTier::Fuzzy] {
+        for (i, entry) in entries.iter().enumerate() {
+            if reported[i] {
+                continue;
+            }
+            let text_lower = &keys[i];
+            let score = match tier {
+                MatchTier::Exact => text_lower.contains(&query_lower).then_some(1.0),
+                MatchTier::InOrder => matches_in_order(text_lower, &query_words).then_some(1.0),
+                MatchTier::Fuzzy => match fuzzy_word_count(text_lower, &query_words) {
+                    0 => None,
+                    n => Some(n as f64 / query_words.len() as f64),
+                },
+            };
+            if let Some(score) = score {
+                reported[i] = true;
+                matches.push((tier, DialogueMatch { entry, score }));
+            }
         }
     }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(start: f64, end: f64, text: &str) -> SubtitleEntry {
+        SubtitleEntry { index: 0, start, end, text: text.to_string() }
+    }
+
+    /// Write `content` to a temp file with the given extension, for the path-based parsers
+    fn sample(content: &str, ext: &str) -> tempfile::NamedTempFile {
+        let file = tempfile::Builder::new().suffix(ext).tempfile().unwrap();
+        fs::write(file.path(), content).unwrap();
+        file
+    }
 
-    bail!("Could not find dialogue: \"{}\"", query)
+    #[test]
+    fn parses_cues_without_numbers() {
+        let file = sample("00:00:01,000 --> 00:00:02,000\nFirst\n\n00:00:03,000 --> 00:00:04,500\nSecond\n", ".srt");
+        let entries = parse_srt(file.path()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].index, entries[0].text.as_str()), (1, "First"));
+        assert_eq!((entries[1].index, entries[1].start, entries[1].end), (2, 3.0, 4.5));
+    }
+
+    #[test]
+    fn parses_single_text_line_cues() {
+        let file = sample("7\n00:00:01,000 --> 00:00:02,000\nOnly line\n\n8\n00:00:02,000 --> 00:00:03,000\nA\nB\n", ".srt");
+        let entries = parse_srt(file.path()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].index, entries[0].text.as_str()), (7, "Only line"));
+        assert_eq!((entries[1].index, entries[1].text.as_str()), (8, "A B"));
+    }
+
+    #[test]
+    fn dialogue_search_ignores_accents() {
+        let entries = [entry(1.0, 2.0, "Un café, s'il vous plaît"), entry(3.0, 4.0, "El niño está aquí")];
+        assert_eq!(find_dialogue(&entries, "cafe", false).unwrap().entry.start, 1.0);
+        assert_eq!(find_dialogue(&entries, "nino esta", false).unwrap().entry.start, 3.0);
+    }
+
+    #[test]
+    fn exact_diacritics_keeps_accents() {
+        let entries = [entry(1.0, 2.0, "Un café, s'il vous plaît")];
+        assert!(find_dialogue(&entries, "cafe", true).is_err());
+        assert!(find_dialogue(&entries, "café", true).is_ok());
+    }
+
+    #[test]
+    fn fuzzy_match_needs_half_the_words() {
+        let entries = [entry(1.0, 2.0, "The ship sails at dawn")];
+        assert!(find_dialogue(&entries, "ship leaves tonight", false).is_err());
+        assert_eq!(find_dialogue(&entries, "ship sails tonight early", false).unwrap().entry.start, 1.0);
+    }
+
+    #[test]
+    fn merges_held_caption() {
+        let merged = merge_held_captions(vec![entry(1.0, 2.0, "Hold on."), entry(2.0, 3.5, "Hold on.")]);
+        assert_eq!(merged.len(), 1);
+        assert_eq!((merged[0].start, merged[0].end), (1.0, 3.5));
+    }
+
+    #[test]
+    fn keeps_repeats_further_apart_than_the_gap() {
+        let later = 2.0 + HELD_CAPTION_GAP + 0.1;
+        let merged = merge_held_captions(vec![entry(1.0, 2.0, "What?"), entry(later, later + 1.0, "What?")]);
+        assert_eq!(merged.len(), 2);
+    }
+
+    #[test]
+    fn keeps_non_adjacent_duplicates() {
+        let merged = merge_held_captions(vec![
+            entry(1.0, 2.0, "No."),
+            entry(2.0, 3.0, "Yes."),
+            entry(3.0, 4.0, "No."),
+        ]);
+        let texts: Vec<&str> = merged.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(texts, ["No.", "Yes.", "No."]);
+    }
+
+    #[test]
+    fn every_format_feeds_the_same_entries() {
+        let samples = [
+            ("1\n00:00:01,000 --> 00:00:02,000\nHello there\n\n2\n00:00:03,000 --> 00:00:04,000\nGeneral Kenobi\n", ".srt"),
+            ("WEBVTT\n\n00:01.000 --> 00:02.000\nHello there\n\n00:03.000 --> 00:04.000\nGeneral Kenobi\n", ".vtt"),
+            ("[00:01.00]Hello there\n[00:03.00]General Kenobi\n", ".lrc"),
+        ];
+        for (content, ext) in samples {
+            let file = sample(content, ext);
+            let entries = parse_subtitles(file.path()).unwrap();
+            let cues: Vec<(f64, &str)> = entries.iter().map(|e| (e.start, e.text.as_str())).collect();
+            assert_eq!(cues, [(1.0, "Hello there"), (3.0, "General Kenobi")], "{}", ext);
+            assert_eq!(find_dialogue(&entries, "kenobi", false).unwrap().entry.start, 3.0, "{}", ext);
+        }
+    }
+
+    #[test]
+    fn wraps_at_word_boundaries() {
+        assert_eq!(wrap_text("the quick brown fox jumps", 10), "the quick\nbrown fox\njumps");
+        assert_eq!(wrap_text("  short   line ", 20), "short line");
+    }
+
+    #[test]
+    fn wrap_gives_long_words_their_own_line() {
+        assert_eq!(wrap_text("a supercalifragilistic word", 8), "a\nsupercalifragilistic\nword");
+    }
+
+    #[test]
+    fn wrap_counts_graphemes() {
+        assert_eq!(wrap_text("café café café", 9), "café café\ncafé");
+        assert_eq!(wrap_text("🐈‍⬛🐈‍⬛ 🐈‍⬛🐈‍⬛", 5), "🐈‍⬛🐈‍⬛ 🐈‍⬛🐈‍⬛");
+    }
+
+    #[test]
+    fn decodes_named_and_numeric_entities() {
+        assert_eq!(decode_html_entities("Tom &amp; Jerry &quot;live&quot;"), "Tom & Jerry \"live\"");
+        assert_eq!(decode_html_entities("it&#39;s &#x2014; fine&hellip;"), "it's — fine…");
+        assert_eq!(decode_html_entities("&lrm;Hello&#8206;"), "Hello");
+    }
+
+    #[test]
+    fn leaves_unknown_entities_and_bare_ampersands() {
+        assert_eq!(decode_html_entities("R&D &bogus; & more"), "R&D &bogus; & more");
+        assert_eq!(decode_html_entities("&amp;amp;"), "&amp;");
+    }
+
+    #[test]
+    fn auto_caption_entities_are_decoded_and_searchable() {
+        let file = sample(
+            "1\n00:00:01,000 --> 00:00:03,000\n&gt;&gt; I don&#39;t know &amp; I don&#39;t care\n\n\
+             2\n00:00:03,000 --> 00:00:05,000\n&lrm;[Music]&nbsp;&quot;okay&quot;\n",
+            ".srt",
+        );
+        let entries = parse_srt(file.path()).unwrap();
+        assert_eq!(entries[0].text, ">> I don't know & I don't care");
+        assert_eq!(entries[1].text, "[Music] \"okay\"");
+        assert_eq!(find_dialogue(&entries, "don't know & I", false).unwrap().entry.start, 1.0);
+    }
+
+    #[test]
+    fn overlapping_cues_survive_a_round_trip() {
+        let original = "1\n00:00:01,000 --> 00:00:04,000\nALICE: Wait for me\n\n\
+                        2\n00:00:02,500 --> 00:00:03,500\nBOB: Hurry up\n\n\
+                        3\n00:00:01,000 --> 00:00:02,000\nNARRATOR: Later\n";
+        let entries = parse_srt(sample(original, ".srt").path()).unwrap();
+        let spans = |entries: &[SubtitleEntry]| -> Vec<(f64, f64, String)> {
+            let mut spans: Vec<_> = entries.iter().map(|e| (e.start, e.end, e.text.clone())).collect();
+            spans.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.2.cmp(&b.2)));
+            spans
+        };
+
+        for ext in [".srt", ".vtt"] {
+            let out = tempfile::Builder::new().suffix(ext).tempfile().unwrap();
+            if ext == ".vtt" {
+                write_vtt(&entries, out.path()).unwrap();
+            } else {
+                write_srt(&entries, out.path()).unwrap();
+            }
+            let reparsed = parse_subtitles(out.path()).unwrap();
+            assert_eq!(spans(&reparsed), spans(&entries), "{}", ext);
+            // Renumbered in start order, keeping file order for cues that start together
+            let texts: Vec<&str> = reparsed.iter().map(|e| e.text.as_str()).collect();
+            assert_eq!(texts, ["ALICE: Wait for me", "NARRATOR: Later", "BOB: Hurry up"], "{}", ext);
+        }
+    }
 }
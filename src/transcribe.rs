@@ -0,0 +1,121 @@
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::srt;
+
+/// A speech-to-text tool found on PATH
+pub enum Whisper {
+    /// whisper.cpp (`whisper-cli`, or `whisper-cpp` in some packages); needs a ggml model file
+    Cpp(PathBuf),
+    /// openai-whisper's `whisper` script; takes a model name and downloads it on first use
+    OpenAi(PathBuf),
+}
+
+impl Whisper {
+    pub fn find() -> Result<Self> {
+        if let Ok(path) = which::which("whisper-cli").or_else(|_| which::which("whisper-cpp")) {
+            return Ok(Whisper::Cpp(path));
+        }
+        if let Ok(path) = which::which("whisper") {
+            return Ok(Whisper::OpenAi(path));
+        }
+        bail!(
+            "--transcribe needs whisper.cpp (whisper-cli) or openai-whisper (`pip install openai-whisper`) on PATH"
+        )
+    }
+}
+
+/// Transcribe the audio of `video_path` into an SRT file in `temp_dir`.
+///
+/// With a `range`, only that stretch of audio is transcribed and the cues are shifted
+/// back to source time so they line up with the rest of the pipeline.
+pub fn transcribe(
+    ffmpeg: &Path,
+    whisper: &Whisper,
+    model: Option<&str>,
+    video_path: &Path,
+    range: (f64, Option<f64>),
+    lang: &str,
+    temp_dir: &Path,
+) -> Result<PathBuf> {
+    let (offset, end) = range;
+    let audio = temp_dir.join("transcribe.wav");
+
+    let mut cmd = Command::new(ffmpeg);
+    cmd.arg("-y").arg("-ss").arg(format!("{}", offset));
+    if let Some(end) = end {
+        cmd.arg("-to").arg(format!("{}", end));
+    }
+    let status = cmd
+        .arg("-i")
+        .arg(video_path)
+        .arg("-vn")
+        .arg("-ac")
+        .arg("1")
+        .arg("-ar")
+        .arg("16000")
+        .arg("-c:a")
+        .arg("pcm_s16le")
+        .arg(&audio)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("Failed to run ffmpeg for audio extraction")?;
+
+    if !status.success() {
+        bail!("ffmpeg failed to extract audio for transcription (does the video have sound?)");
+    }
+
+    let output_base = temp_dir.join("transcribe");
+    let status = match whisper {
+        Whisper::Cpp(bin) => {
+            let Some(model) = model else {
+                bail!("whisper.cpp needs a model file: pass --whisper-model path/to/ggml-base.bin");
+            };
+            Command::new(bin)
+                .arg("-m")
+                .arg(model)
+                .arg("-l")
+                .arg(lang)
+                .arg("-osrt")
+                .arg("-of")
+                .arg(&output_base)
+                .arg(&audio)
+                .stdout(Stdio::null())
+                .status()
+        }
+        Whisper::OpenAi(bin) => Command::new(bin)
+            .arg(&audio)
+            .arg("--model")
+            .arg(model.unwrap_or("base"))
+            .arg("--language")
+            .arg(lang)
+            .arg("--output_format")
+            .arg("srt")
+            .arg("--output_dir")
+            .arg(temp_dir)
+            .stdout(Stdio::null())
+            .status(),
+    }
+    .context("Failed to run whisper")?;
+
+    let srt_path = output_base.with_extension("srt");
+    if !status.success() || !srt_path.exists() {
+        bail!("whisper failed to transcribe the audio");
+    }
+
+    if offset > 0.0 {
+        let entries: Vec<srt::SubtitleEntry> = srt::parse_srt(&srt_path)?
+            .into_iter()
+            .map(|e| srt::SubtitleEntry {
+                start: e.start + offset,
+                end: e.end + offset,
+                ..e
+            })
+            .collect();
+        srt::write_srt(&entries, &srt_path)?;
+    }
+
+    Ok(srt_path)
+}
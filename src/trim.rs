@@ -0,0 +1,132 @@
+use anyhow::{bail, Context, Result};
+use dialoguer::Select;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+const ACTIONS: &[&str] = &[
+    "Back 1s",
+    "Back 1 frame",
+    "Forward 1 frame",
+    "Forward 1s",
+    "Set start here",
+    "Set end here",
+    "Jump to start",
+    "Jump to end",
+    "Preview frame",
+    "Done",
+];
+
+/// Step through the source around a rough clip and pick exact in/out points.
+///
+/// Previews are extracted as PNGs into `temp_dir` and shown inline on terminals that
+/// speak the iTerm2 image protocol (iTerm2, WezTerm); elsewhere the file path is printed.
+pub fn interactive_trim(
+    ffmpeg: &Path,
+    video_path: &Path,
+    temp_dir: &Path,
+    start: f64,
+    end: f64,
+    fps: u32,
+) -> Result<(f64, f64)> {
+    let frame = 1.0 / fps.max(1) as f64;
+    let (mut start, mut end) = (start, end);
+    let mut position = start;
+    let mut selected = 0;
+
+    loop {
+        println!(
+            "\nPosition {}  |  start {}  end {}  ({:.2}s)",
+            format_precise(position),
+            format_precise(start),
+            format_precise(end),
+            end - start
+        );
+
+        selected = Select::new()
+            .with_prompt("Trim")
+            .items(ACTIONS)
+            .default(selected)
+            .interact()
+            .context("Failed to get user selection")?;
+
+        match ACTIONS[selected] {
+            "Back 1s" => position = (position - 1.0).max(0.0),
+            "Back 1 frame" => position = (position - frame).max(0.0),
+            "Forward 1 frame" => position += frame,
+            "Forward 1s" => position += 1.0,
+            "Set start here" => start = position,
+            "Set end here" => end = position,
+            "Jump to start" => position = start,
+            "Jump to end" => position = end,
+            "Preview frame" => preview_frame(ffmpeg, video_path, temp_dir, position)?,
+            _ => {
+                if end <= start {
+                    eprintln!("End must be after start");
+                    continue;
+                }
+                return Ok((start, end));
+            }
+        }
+    }
+}
+
+fn preview_frame(ffmpeg: &Path, video_path: &Path, temp_dir: &Path, position: f64) -> Result<()> {
+    let preview = temp_dir.join("preview.png");
+
+    let status = Command::new(ffmpeg)
+        .arg("-y")
+        .arg("-ss")
+        .arg(format!("{}", position))
+        .arg("-i")
+        .arg(video_path)
+        .arg("-frames:v")
+        .arg("1")
+        .arg("-vf")
+        .arg("scale=320:-1")
+        .arg(&preview)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("Failed to run ffmpeg for preview")?;
+
+    if !status.success() {
+        bail!("ffmpeg failed to extract a preview frame");
+    }
+
+    let term = env::var("TERM_PROGRAM").unwrap_or_default();
+    if term == "iTerm.app" || term == "WezTerm" {
+        let bytes = fs::read(&preview)?;
+        println!(
+            "\x1b]1337;File=inline=1;size={}:{}\x07",
+            bytes.len(),
+            base64_encode(&bytes)
+        );
+    } else {
+        println!("Preview: {}", preview.display());
+    }
+
+    Ok(())
+}
+
+fn format_precise(secs: f64) -> String {
+    let whole = secs.floor();
+    format!("{}.{:03}", crate::format_clock(whole), ((secs - whole) * 1000.0).floor() as u32)
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        out.push(TABLE[(n >> 18) as usize & 63] as char);
+        out.push(TABLE[(n >> 12) as usize & 63] as char);
+        out.push(if chunk.len() > 1 { TABLE[(n >> 6) as usize & 63] as char } else { '=' });
+        out.push(if chunk.len() > 2 { TABLE[n as usize & 63] as char } else { '=' });
+    }
+    out
+}
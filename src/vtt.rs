@@ -0,0 +1,85 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+use crate::srt::SubtitleEntry;
+
+pub fn parse_vtt(path: &Path) -> Result<Vec<SubtitleEntry>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read subtitle file: {}", path.display()))?;
+
+    Ok(parse_vtt_str(&content))
+}
+
+pub fn parse_vtt_str(content: &str) -> Vec<SubtitleEntry> {
+    let content = content.replace("\r\n", "\n");
+
+    // VTT timestamp format: [HH:]MM:SS.mmm --> [HH:]MM:SS.mmm [cue settings]
+    let time_re = Regex::new(
+        r"(?:(\d+):)?(\d{2}):(\d{2})\.(\d{3})\s*-->\s*(?:(\d+):)?(\d{2}):(\d{2})\.(\d{3})",
+    )
+    .unwrap();
+    // Voice spans, styling and YouTube's inline <00:00:01.000><c> karaoke timing
+    let tag_re = Regex::new(r"<[^>]*>").unwrap();
+
+    let mut entries = Vec::new();
+
+    for block in content.split("\n\n") {
+        let lines: Vec<&str> = block.lines().collect();
+
+        let Some(ts_index) = lines.iter().position(|l| time_re.is_match(l)) else {
+            // WEBVTT header, NOTE, STYLE and REGION blocks
+            continue;
+        };
+
+        let caps = time_re.captures(lines[ts_index]).unwrap();
+        let start = parse_vtt_time(caps.get(1).map(|m| m.as_str()), &caps[2], &caps[3], &caps[4]);
+        let end = parse_vtt_time(caps.get(5).map(|m| m.as_str()), &caps[6], &caps[7], &caps[8]);
+
+        let text = tag_re.replace_all(&lines[ts_index + 1..].join(" "), "").into_owned();
+        let text = crate::srt::decode_html_entities(&text).trim().to_string();
+
+        if !text.is_empty() {
+            entries.push(SubtitleEntry {
+                index: entries.len() + 1,
+                start,
+                end,
+                text,
+            });
+        }
+    }
+
+    entries
+}
+
+fn parse_vtt_time(hours: Option<&str>, mins: &str, secs: &str, millis: &str) -> f64 {
+    let h: f64 = hours.and_then(|h| h.parse().ok()).unwrap_or(0.0);
+    let m: f64 = mins.parse().unwrap_or(0.0);
+    let s: f64 = secs.parse().unwrap_or(0.0);
+    let ms: f64 = millis.parse().unwrap_or(0.0);
+
+    h * 3600.0 + m * 60.0 + s + ms / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_header_and_note_blocks() {
+        let entries = parse_vtt_str("WEBVTT\n\nNOTE made by hand\n\n00:01.000 --> 00:02.500\nHello there\n");
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].start, entries[0].end, entries[0].text.as_str()), (1.0, 2.5, "Hello there"));
+    }
+
+    #[test]
+    fn strips_tags_and_cue_settings() {
+        let entries = parse_vtt_str(
+            "WEBVTT\r\n\r\n1\r\n01:00:01.000 --> 01:00:02.000 align:start position:0%\r\n\
+             <v Bob>Hi<00:00:01.500><c> you</c>\r\n",
+        );
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].start, entries[0].text.as_str()), (3601.0, "Hi you"));
+    }
+}